@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use terrain_generator::perlin_noise::{grid_coords, PerlinMap};
+
+// Scalar noise per sample against the batch noise_grid for a 1024x1024 height grid
+// Run with: cargo bench --bench noise_batch (tests/noise_batch.rs checks both give the same values)
+const SIZE: usize = 1024;
+const STEP: f32 = 0.05;
+
+fn scalar(p_map: &PerlinMap, out: &mut [f32]) {
+    for row in 0 .. SIZE {
+        for col in 0 .. SIZE {
+            out[row * SIZE + col] = p_map.noise((col as f32 * STEP) + 0.5, (row as f32 * STEP) + 0.5);
+        }
+    }
+}
+
+fn bench_noise(c: &mut Criterion) {
+    let mut p_map = PerlinMap::new();
+    p_map.set_hashed(7);
+
+    let xs = grid_coords(0.5, STEP, SIZE);
+    let ys = grid_coords(0.5, STEP, SIZE);
+    let mut scalar_out = vec![0.0; SIZE * SIZE];
+    let mut batch_out = vec![0.0; SIZE * SIZE];
+
+    let mut group = c.benchmark_group("noise_1024x1024");
+    group.sample_size(10);
+    group.bench_function("scalar", |b| b.iter(|| scalar(black_box(&p_map), &mut scalar_out)));
+    group.bench_function("batch", |b| b.iter(|| p_map.noise_grid(black_box(&xs), black_box(&ys), &mut batch_out)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_noise);
+criterion_main!(benches);
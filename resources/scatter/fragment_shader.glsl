@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec3 shadePosition;
+out vec4 fragColor;
+
+uniform vec3 color;
+
+void main() {
+    // The models have no normals, the face normal comes from the screen-space derivatives of the position
+    vec3 normal = normalize(cross(dFdx(shadePosition), dFdy(shadePosition)));
+    float light = 0.45 + 0.55 * abs(dot(normal, normalize(vec3(0.3, 0.6, 0.75))));
+
+    fragColor = vec4(color * light, 1.0);
+}
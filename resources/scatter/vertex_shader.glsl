@@ -0,0 +1,21 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+// Per instance: xyz relative to the chunk and scale, rotation around z
+layout (location = 1) in vec4 instance;
+layout (location = 2) in float rotation;
+
+uniform mat4 transform;
+// Model heights are multiplied by this, so the objects keep their shape in the view that stretches the terrain heights
+uniform float heightScale;
+
+out vec3 shadePosition;
+
+void main() {
+    // glTF models are y up, the terrain is z up
+    vec3 local = vec3(position.x, -position.z, position.y * heightScale) * instance.w;
+    vec3 rotated = vec3(cos(rotation) * local.x - sin(rotation) * local.y, sin(rotation) * local.x + cos(rotation) * local.y, local.z);
+
+    gl_Position = transform * vec4(instance.xyz + rotated, 1.0);
+    shadePosition = gl_Position.xyz;
+}
@@ -1,13 +1,45 @@
 #version 330 core
 
 layout (location = 0) in vec3 position;
+// Primary biome id, secondary biome id and the weight of the secondary biome
+layout (location = 1) in vec3 biome;
 
 uniform mat4 transform;
 
+// Colour ramp: rampSize stops sorted by height, colours are blended between neighbouring stops
+uniform int rampSize;
+uniform float rampHeights[8];
+uniform vec3 rampColors[8];
+
+// Biome colours by biome id, used instead of the ramp when useBiomes is set
+uniform int useBiomes;
+uniform vec3 biomeColors[11];
+
 // Output barycentric coordinates to highlight edges
 out vec3 barycentricCoord;
 out vec3 faceColor;
 
+vec3 rampColor(float height) {
+    if (height <= rampHeights[0]) {
+        return rampColors[0];
+    }
+
+    for (int i = 1; i < rampSize; i++) {
+        if (height <= rampHeights[i]) {
+            float t = (height - rampHeights[i - 1]) / max(rampHeights[i] - rampHeights[i - 1], 0.000001);
+            return mix(rampColors[i - 1], rampColors[i], t);
+        }
+    }
+
+    return rampColors[rampSize - 1];
+}
+
+vec3 biomeColor(float id, float height) {
+    // The ocean (id 0) keeps the ramp so its depth still shows
+    int index = int(id + 0.5);
+    return index == 0 ? rampColor(height) : biomeColors[index];
+}
+
 void main() {
     gl_Position = transform * vec4(position, 1.0);
     
@@ -22,6 +54,10 @@ void main() {
         barycentricCoord = vec3(0.0, 0.0, 1.0);
     }
     
-    // Generate color based on height
-    faceColor = vec3(0.0, position.z * 0.5 + 0.5, position.z * 0.2 + 0.8);
+    // Color based on biome or height
+    if (useBiomes != 0) {
+        faceColor = mix(biomeColor(biome.x, position.z), biomeColor(biome.y, position.z), biome.z);
+    } else {
+        faceColor = rampColor(position.z);
+    }
 }
\ No newline at end of file
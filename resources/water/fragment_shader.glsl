@@ -0,0 +1,25 @@
+#version 330 core
+
+in float depth;
+out vec4 fragColor;
+
+uniform vec3 shallowColor;
+uniform vec3 deepColor;
+uniform float deepDepth;
+uniform float opacity;
+uniform float foamWidth;
+
+void main() {
+    // Above the ground (the margin triangles on the beach)
+    if (depth < -foamWidth) {
+        discard;
+    }
+
+    // Colour and opacity go from shallow to deep with the depth
+    float t = clamp(depth / deepDepth, 0.0, 1.0);
+    vec4 water = vec4(mix(shallowColor, deepColor, t), mix(opacity, 1.0, t));
+
+    // Foam fades out from the shoreline
+    float foam = 1.0 - clamp(abs(depth) / max(foamWidth, 0.000001), 0.0, 1.0);
+    fragColor = mix(water, vec4(1.0, 1.0, 1.0, 0.9), foam);
+}
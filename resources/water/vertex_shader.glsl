@@ -0,0 +1,15 @@
+#version 330 core
+
+// Point on the water surface and how deep the water below it is
+layout (location = 0) in vec3 position;
+layout (location = 1) in float waterDepth;
+
+uniform mat4 transform;
+
+// Interpolated so every fragment knows its own depth
+out float depth;
+
+void main() {
+    gl_Position = transform * vec4(position, 1.0);
+    depth = waterDepth;
+}
@@ -0,0 +1,228 @@
+use crate::fractal_noise::FractalNoise;
+use crate::height_source::HeightSource;
+use crate::perlin_noise::PerlinMap;
+
+// Biomes
+// Temperature and moisture fields from their own seeded noise, temperature falls with altitude and with the distance
+// from the equator, and a Whittaker-style table turns the two into a biome
+// Near the border of a table cell the neighbouring biome is reported as well, with a weight for blending the two
+/* Example:
+let biomes = BiomeMap::new(42, BiomeSettings::default());
+
+let sample = biomes.classify(x, y, height);
+println!("{} ({:.0}% {})", sample.primary.name(), sample.blend * 100.0, sample.secondary.name());
+
+// Per chunk vertex: primary id, secondary id, blend
+let vertex_biomes = biomes.vertex_biomes(&chunk.vertices, chunk.origin_x, chunk.origin_y);
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Ocean,
+    Beach,
+    Snow,
+    Tundra,
+    Taiga,
+    Grassland,
+    TemperateForest,
+    TemperateRainforest,
+    Desert,
+    Savanna,
+    TropicalRainforest
+}
+
+// Rows from cold to hot, columns from dry to wet
+const WHITTAKER: [[Biome; 3]; 3] = [
+    [Biome::Tundra, Biome::Taiga, Biome::Taiga],
+    [Biome::Grassland, Biome::TemperateForest, Biome::TemperateRainforest],
+    [Biome::Desert, Biome::Savanna, Biome::TropicalRainforest]
+];
+
+impl Biome {
+    pub const ALL: [Biome; 11] = [
+        Biome::Ocean, Biome::Beach, Biome::Snow, Biome::Tundra, Biome::Taiga, Biome::Grassland,
+        Biome::TemperateForest, Biome::TemperateRainforest, Biome::Desert, Biome::Savanna, Biome::TropicalRainforest
+    ];
+
+    // Index in ALL, the id the shader gets
+    pub fn id(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_id(id: u32) -> Option<Biome> {
+        Biome::ALL.get(id as usize).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Ocean => "ocean",
+            Biome::Beach => "beach",
+            Biome::Snow => "snow",
+            Biome::Tundra => "tundra",
+            Biome::Taiga => "taiga",
+            Biome::Grassland => "grassland",
+            Biome::TemperateForest => "temperate_forest",
+            Biome::TemperateRainforest => "temperate_rainforest",
+            Biome::Desert => "desert",
+            Biome::Savanna => "savanna",
+            Biome::TropicalRainforest => "tropical_rainforest"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Biome> {
+        Biome::ALL.iter().copied().find(|biome| biome.name() == name)
+    }
+
+    // Default terrain colour, the ocean is coloured by the height ramp instead
+    pub fn color(self) -> [f32; 3] {
+        match self {
+            Biome::Ocean => [0.0, 0.2, 0.6],
+            Biome::Beach => [0.86, 0.8, 0.55],
+            Biome::Snow => [0.95, 0.95, 0.97],
+            Biome::Tundra => [0.6, 0.62, 0.55],
+            Biome::Taiga => [0.2, 0.4, 0.3],
+            Biome::Grassland => [0.55, 0.7, 0.3],
+            Biome::TemperateForest => [0.25, 0.55, 0.2],
+            Biome::TemperateRainforest => [0.1, 0.45, 0.25],
+            Biome::Desert => [0.9, 0.75, 0.45],
+            Biome::Savanna => [0.75, 0.7, 0.35],
+            Biome::TropicalRainforest => [0.05, 0.4, 0.1]
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiomeSettings {
+    pub size: f32, // Size of the temperature and moisture features in world units
+    pub temperature_offset: f32, // Added to the temperature everywhere, positive is warmer
+    pub lapse_rate: f32, // Temperature lost per unit of height above the sea level
+    pub latitude_gradient: f32, // Temperature lost per world unit away from the equator
+    pub equator: f32, // World y of the equator
+    pub sea_level: f32,
+    pub beach_height: f32, // Beaches reach this far above the sea level
+    pub snow_temperature: f32, // Colder is snow
+    pub blend: f32 // Temperature and moisture distance over which neighbouring biomes are blended
+}
+
+impl Default for BiomeSettings {
+    fn default() -> Self {
+        BiomeSettings {
+            size: 4.0,
+            temperature_offset: 0.0,
+            lapse_rate: 1.0,
+            latitude_gradient: 0.0,
+            equator: 0.0,
+            sea_level: 0.0,
+            beach_height: 0.02,
+            snow_temperature: 0.1,
+            blend: 0.05
+        }
+    }
+}
+
+// Biome at a position, blend is the weight of the secondary biome (0.0 .. 0.5, 0.5 right on the border)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeSample {
+    pub primary: Biome,
+    pub secondary: Biome,
+    pub blend: f32
+}
+
+pub struct BiomeMap {
+    temperature: FractalNoise,
+    moisture: FractalNoise,
+    settings: BiomeSettings
+}
+
+impl BiomeMap {
+    pub fn new(seed: u64, settings: BiomeSettings) -> Self {
+        // Seeds of their own, so the climate does not follow the height noise
+        let noise = |seed: u64| {
+            let mut perlin_map = PerlinMap::new();
+            perlin_map.set_hashed(seed);
+            FractalNoise::new(perlin_map, 3)
+        };
+
+        BiomeMap {
+            temperature: noise(seed.wrapping_add(2)),
+            moisture: noise(seed.wrapping_add(3)),
+            settings
+        }
+    }
+
+    pub fn get_settings(&self) -> &BiomeSettings {
+        &self.settings
+    }
+
+    // 0.0 (coldest) .. 1.0 (hottest)
+    pub fn temperature(&self, x: f32, y: f32, height: f32) -> f32 {
+        let s = &self.settings;
+        let base = self.temperature.height(x / s.size, y / s.size) + 0.5 + s.temperature_offset;
+        let altitude = (height - s.sea_level).max(0.0) * s.lapse_rate;
+        let latitude = (y - s.equator).abs() * s.latitude_gradient;
+        (base - altitude - latitude).clamp(0.0, 1.0)
+    }
+
+    // 0.0 (driest) .. 1.0 (wettest)
+    pub fn moisture(&self, x: f32, y: f32) -> f32 {
+        let s = &self.settings;
+        (self.moisture.height(x / s.size, y / s.size) + 0.5).clamp(0.0, 1.0)
+    }
+
+    pub fn classify(&self, x: f32, y: f32, height: f32) -> BiomeSample {
+        let (temperature, moisture) = (self.temperature(x, y, height), self.moisture(x, y));
+        let primary = self.lookup(temperature, moisture, height);
+
+        // The closest different biome within the blend distance along either axis
+        let blend = self.settings.blend;
+        let directions = [(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)];
+        let at = |(dt, dm): (f32, f32), distance: f32| self.lookup(temperature + dt * distance, moisture + dm * distance, height);
+
+        if blend > 0.0 {
+            for step in 1 ..= 4 {
+                let distance = blend * step as f32 / 4.0;
+                let Some(&direction) = directions.iter().find(|direction| at(**direction, distance) != primary) else {
+                    continue;
+                };
+
+                // Narrow down where the border is
+                let (mut inside, mut outside) = (distance - blend / 4.0, distance);
+                for _ in 0 .. 6 {
+                    let middle = (inside + outside) / 2.0;
+                    if at(direction, middle) == primary { inside = middle } else { outside = middle }
+                }
+
+                // Half and half on the border, fading to the primary biome at the blend distance
+                let weight = 0.5 * (1.0 - (inside + outside) / 2.0 / blend);
+                return BiomeSample { primary, secondary: at(direction, outside), blend: weight };
+            }
+        }
+
+        BiomeSample { primary, secondary: primary, blend: 0.0 }
+    }
+
+    fn lookup(&self, temperature: f32, moisture: f32, height: f32) -> Biome {
+        let s = &self.settings;
+        if height < s.sea_level {
+            Biome::Ocean
+        } else if height < s.sea_level + s.beach_height {
+            Biome::Beach
+        } else if temperature < s.snow_temperature {
+            Biome::Snow
+        } else {
+            // The rest of the temperature range split into three bands
+            let t = (temperature - s.snow_temperature) / (1.0 - s.snow_temperature).max(f32::EPSILON);
+            let row = ((t * 3.0) as usize).min(2);
+            let column = ((moisture.clamp(0.0, 1.0) * 3.0) as usize).min(2);
+            WHITTAKER[row][column]
+        }
+    }
+
+    // Primary id, secondary id and blend for every vertex of an xyz mesh placed at (origin_x, origin_y)
+    pub fn vertex_biomes(&self, vertices: &[f32], origin_x: f32, origin_y: f32) -> Vec<f32> {
+        vertices.chunks_exact(3).flat_map(|v| {
+            let sample = self.classify(origin_x + v[0], origin_y + v[1], v[2]);
+            [sample.primary.id() as f32, sample.secondary.id() as f32, sample.blend]
+        }).collect()
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::biomes::BiomeMap;
+use crate::culling::Aabb;
+use crate::functions::generate_chunk_mesh;
+use crate::height_source::SharedHeightSource;
+use crate::scatter::{Instance, Scatter};
+
+// Chunk Manager
+// Splits the world into square chunks arranged in a quadtree: a chunk at level L covers 2^L x 2^L level 0 chunks
+// with the same number of cells, so distant terrain uses coarser grids
+// Chunks around the viewer are selected by screen-space error, generated on a pool of worker threads
+// and handed back through a channel, chunks that are no longer needed are evicted
+/* Example:
+let mut chunks = ChunkManager::new(16, 0.2, Arc::new(perlin_map), 4);
+chunks.set_lod(LodSettings::default());
+chunks.set_max_uploads_per_frame(4);
+
+// Every frame
+let changes = chunks.update(player.x, player.y, view_distance);
+for key in changes.loaded { upload chunks.get(key) to the GPU }
+for key in changes.evicted { free the GPU buffers of key }
+*/
+
+pub type ChunkCoord = (i32, i32);
+
+// Quadtree node: grid coordinate in units of the node's own size, and its LOD level (0 is the finest)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkKey {
+    pub coord: ChunkCoord,
+    pub level: u32
+}
+
+impl ChunkKey {
+    pub fn new(x: i32, y: i32, level: u32) -> Self {
+        ChunkKey { coord: (x, y), level }
+    }
+
+    pub fn children(&self) -> [ChunkKey; 4] {
+        let (x, y) = (self.coord.0 * 2, self.coord.1 * 2);
+        let level = self.level - 1;
+        [
+            ChunkKey::new(x, y, level),
+            ChunkKey::new(x + 1, y, level),
+            ChunkKey::new(x, y + 1, level),
+            ChunkKey::new(x + 1, y + 1, level)
+        ]
+    }
+
+    // The node at a coarser level that contains this one
+    pub fn ancestor(&self, level: u32) -> ChunkKey {
+        let shift = level - self.level;
+        // Arithmetic shift rounds towards negative infinity, which keeps negative coordinates in the right parent
+        ChunkKey::new(self.coord.0 >> shift, self.coord.1 >> shift, level)
+    }
+
+    // True when one node contains the other
+    pub fn overlaps(&self, other: &ChunkKey) -> bool {
+        if self.level >= other.level {
+            other.ancestor(self.level) == *self
+        } else {
+            self.ancestor(other.level) == *other
+        }
+    }
+}
+
+pub struct Chunk {
+    pub key: ChunkKey,
+    pub origin_x: f32, // World position of the chunk's (0, 0) vertex
+    pub origin_y: f32,
+    pub vertices: Vec<f32>, // Relative to the origin
+    pub indices: Vec<i32>,
+    pub triangle_count: i32,
+    // Box around the vertices relative to the origin, from the chunk's lowest (skirts included) to its highest point
+    pub bounds: Aabb,
+    // Primary id, secondary id and blend of every vertex, empty without a biome map
+    pub biomes: Vec<f32>,
+    // Scattered objects, positions relative to the origin like the vertices
+    pub instances: Vec<Instance>
+}
+
+#[derive(Debug, Default)]
+pub struct ChunkChanges {
+    pub loaded: Vec<ChunkKey>,
+    pub evicted: Vec<ChunkKey>
+}
+
+// Level selection: a chunk is split into its four children while its projected error is above pixel_error
+#[derive(Debug, Clone, Copy)]
+pub struct LodSettings {
+    pub max_level: u32,
+    pub pixel_error: f32, // Largest allowed screen-space error in pixels
+    pub screen_factor: f32, // viewport height / (2 * tan(fov / 2)), converts world error at a distance to pixels
+    pub height_error: f32 // Height error of a cell relative to its spacing, used as the geometric error of a level
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        LodSettings {
+            max_level: 4,
+            pixel_error: 4.0,
+            screen_factor: 720.0 / (2.0 * (30.0f32).to_radians().tan()),
+            height_error: 0.1
+        }
+    }
+}
+
+// Everything a worker needs to build a chunk
+#[derive(Clone)]
+struct ChunkGenerator {
+    chunk_size: i32,
+    scale: f32,
+    source: SharedHeightSource,
+    biomes: Option<Arc<BiomeMap>>,
+    scatter: Option<Arc<Scatter>>
+}
+
+impl ChunkGenerator {
+    fn node_extent(&self, level: u32) -> f32 {
+        self.chunk_size as f32 * self.scale * (1u32 << level) as f32
+    }
+
+    fn generate(&self, key: ChunkKey) -> Chunk {
+        // The skirt has to reach below the largest crack, which is at most about one cell of height
+        let skirt_depth = self.scale * (1u32 << key.level) as f32;
+        let (vertices, indices, triangle_count) = generate_chunk_mesh(self.scale, self.chunk_size, key.level, key.coord.0, key.coord.1, skirt_depth, self.source.as_ref());
+        let extent = self.node_extent(key.level);
+        let (origin_x, origin_y) = (key.coord.0 as f32 * extent, key.coord.1 as f32 * extent);
+        let biomes = match &self.biomes {
+            Some(biomes) => biomes.vertex_biomes(&vertices, origin_x, origin_y),
+            None => Vec::new()
+        };
+
+        // Scattered per level 0 chunk, so the objects stay the same when the chunk is split or merged
+        let mut instances = Vec::new();
+        if let Some(scatter) = &self.scatter {
+            let (tiles, tile_size) = (1i32 << key.level, self.node_extent(0));
+            for ty in key.coord.1 * tiles .. (key.coord.1 + 1) * tiles {
+                for tx in key.coord.0 * tiles .. (key.coord.0 + 1) * tiles {
+                    instances.extend(scatter.scatter_tile(self.source.as_ref(), self.biomes.as_deref(), tx, ty, tile_size, key.level));
+                }
+            }
+            for instance in instances.iter_mut() {
+                instance.position[0] -= origin_x;
+                instance.position[1] -= origin_y;
+            }
+        }
+
+        let bounds = Aabb::from_points(&vertices);
+
+        Chunk {
+            key,
+            origin_x,
+            origin_y,
+            vertices,
+            indices,
+            triangle_count,
+            bounds,
+            biomes,
+            instances
+        }
+    }
+}
+
+// A chunk request, tagged with the generation (map version) it was requested for
+struct ChunkJob {
+    key: ChunkKey,
+    generation: u32,
+    generator: ChunkGenerator
+}
+
+pub struct ChunkManager {
+    generator: ChunkGenerator,
+    lod: LodSettings,
+    // Bumped whenever the map changes, results of older generations are discarded
+    generation: u32,
+    max_uploads_per_frame: usize,
+    chunks: HashMap<ChunkKey, Chunk>,
+    // Requested but not handed out yet (queued, generating or waiting in ready)
+    pending: HashSet<ChunkKey>,
+    // Received from the workers but not handed out yet because of the upload cap
+    ready: VecDeque<Chunk>,
+    // Chunks still needed, workers skip requests that are no longer in here
+    wanted: Arc<Mutex<HashSet<ChunkKey>>>,
+    job_sender: Option<Sender<ChunkJob>>,
+    result_receiver: Receiver<(u32, Chunk)>,
+    workers: Vec<JoinHandle<()>>
+}
+
+impl ChunkManager {
+    // worker_count of 0 generates chunks on the calling thread inside update
+    pub fn new(chunk_size: i32, scale: f32, source: SharedHeightSource, worker_count: usize) -> Self {
+        let generator = ChunkGenerator { chunk_size, scale, source, biomes: None, scatter: None };
+        let wanted = Arc::new(Mutex::new(HashSet::new()));
+
+        let (job_sender, job_receiver) = mpsc::channel::<ChunkJob>();
+        let (result_sender, result_receiver) = mpsc::channel::<(u32, Chunk)>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let mut workers = Vec::new();
+        for _ in 0 .. worker_count {
+            let wanted = Arc::clone(&wanted);
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+
+            workers.push(thread::spawn(move || loop {
+                // The lock is only held while waiting for the next job
+                let job = job_receiver.lock().unwrap().recv();
+                let job = match job {
+                    Ok(job) => job,
+                    Err(_) => break // Manager dropped
+                };
+
+                // Cancelled while waiting in the queue
+                if !wanted.lock().unwrap().contains(&job.key) {
+                    continue;
+                }
+
+                let chunk = job.generator.generate(job.key);
+                if result_sender.send((job.generation, chunk)).is_err() {
+                    break;
+                }
+            }));
+        }
+
+        ChunkManager {
+            generator,
+            lod: LodSettings::default(),
+            generation: 0,
+            max_uploads_per_frame: usize::MAX,
+            chunks: HashMap::new(),
+            pending: HashSet::new(),
+            ready: VecDeque::new(),
+            wanted,
+            job_sender: if worker_count > 0 { Some(job_sender) } else { None },
+            result_receiver,
+            workers
+        }
+    }
+
+    pub fn set_lod(&mut self, lod: LodSettings) {
+        self.lod = lod;
+    }
+
+    // Limit how many finished chunks update hands out per call, spreading GPU uploads over several frames
+    pub fn set_max_uploads_per_frame(&mut self, max_uploads: usize) {
+        self.max_uploads_per_frame = max_uploads.max(1);
+    }
+
+    // World size of one chunk side at a level
+    pub fn chunk_extent(&self, level: u32) -> f32 {
+        self.generator.node_extent(level)
+    }
+
+    // Geometric error of a level: how far its surface can be from the finest level
+    fn geometric_error(&self, level: u32) -> f32 {
+        if level == 0 {
+            0.0
+        } else {
+            self.lod.height_error * self.generator.scale * (1u32 << level) as f32
+        }
+    }
+
+    // Error of a level in pixels when seen from a distance
+    pub fn screen_space_error(&self, level: u32, distance: f32) -> f32 {
+        let distance = distance.max(self.generator.scale);
+        self.geometric_error(level) / distance * self.lod.screen_factor
+    }
+
+    // Horizontal distance from (x, y) to the closest point of a chunk
+    fn distance_to(&self, key: &ChunkKey, x: f32, y: f32) -> f32 {
+        let extent = self.chunk_extent(key.level);
+        let (min_x, min_y) = (key.coord.0 as f32 * extent, key.coord.1 as f32 * extent);
+        let dx = (min_x - x).max(0.0).max(x - (min_x + extent));
+        let dy = (min_y - y).max(0.0).max(y - (min_y + extent));
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    fn select(&self, key: ChunkKey, x: f32, y: f32, selected: &mut Vec<ChunkKey>) {
+        if key.level > 0 && self.screen_space_error(key.level, self.distance_to(&key, x, y)) > self.lod.pixel_error {
+            for child in key.children() {
+                self.select(child, x, y, selected);
+            }
+        } else {
+            selected.push(key);
+        }
+    }
+
+    // Chunks needed to cover view_distance around (x, y), coarser further away
+    pub fn select_chunks(&self, x: f32, y: f32, view_distance: f32) -> Vec<ChunkKey> {
+        let top = self.lod.max_level;
+        let extent = self.chunk_extent(top);
+        let mut selected = Vec::new();
+
+        let (min_x, max_x) = (((x - view_distance) / extent).floor() as i32, ((x + view_distance) / extent).floor() as i32);
+        let (min_y, max_y) = (((y - view_distance) / extent).floor() as i32, ((y + view_distance) / extent).floor() as i32);
+        for cy in min_y ..= max_y {
+            for cx in min_x ..= max_x {
+                self.select(ChunkKey::new(cx, cy, top), x, y, &mut selected);
+            }
+        }
+
+        selected
+    }
+
+    // Select the chunks for the viewer at (x, y), request the missing ones, evict the ones that are no longer needed
+    // and return the chunks that finished generating since the last update
+    // A chunk that is no longer selected stays until the chunks replacing it are loaded, so no holes appear while switching levels
+    pub fn update(&mut self, x: f32, y: f32, view_distance: f32) -> ChunkChanges {
+        let mut changes = ChunkChanges::default();
+
+        let mut selected = self.select_chunks(x, y, view_distance);
+        selected.sort_by(|a, b| self.distance_to(a, x, y).total_cmp(&self.distance_to(b, x, y)));
+        let selected_set: HashSet<ChunkKey> = selected.iter().copied().collect();
+
+        // Cancel stale requests and drop finished chunks that are no longer needed
+        self.pending.retain(|key| selected_set.contains(key));
+        self.ready.retain(|chunk| selected_set.contains(&chunk.key));
+
+        // Missing chunks, nearest first so the area around the viewer fills in first
+        let missing: Vec<ChunkKey> = selected
+            .into_iter()
+            .filter(|key| !self.chunks.contains_key(key) && !self.pending.contains(key))
+            .collect();
+
+        // Updated before sending, a worker may pick up a job before this function returns
+        {
+            let mut wanted = self.wanted.lock().unwrap();
+            wanted.clear();
+            wanted.extend(self.pending.iter().copied());
+            wanted.extend(missing.iter().copied());
+        }
+
+        match &self.job_sender {
+            Some(sender) => {
+                for key in missing {
+                    let job = ChunkJob { key, generation: self.generation, generator: self.generator.clone() };
+                    if sender.send(job).is_ok() {
+                        self.pending.insert(key);
+                    }
+                }
+            },
+            None => {
+                for key in missing {
+                    self.ready.push_back(self.generator.generate(key));
+                    self.pending.insert(key);
+                }
+            }
+        }
+
+        // Collect finished chunks, ignoring the ones that were cancelled after they were generated
+        while let Ok((generation, chunk)) = self.result_receiver.try_recv() {
+            if generation == self.generation && self.pending.contains(&chunk.key) {
+                self.ready.push_back(chunk);
+            }
+        }
+
+        while changes.loaded.len() < self.max_uploads_per_frame {
+            match self.ready.pop_front() {
+                Some(chunk) => {
+                    self.pending.remove(&chunk.key);
+                    changes.loaded.push(chunk.key);
+                    self.chunks.insert(chunk.key, chunk);
+                },
+                None => break
+            }
+        }
+
+        // Evict chunks that are not selected, unless a selected chunk covering the same area is still on its way
+        let pending = &self.pending;
+        self.chunks.retain(|key, _| {
+            let keep = selected_set.contains(key) || pending.iter().any(|p| p.overlaps(key));
+            if !keep {
+                changes.evicted.push(*key);
+            }
+            keep
+        });
+
+        changes
+    }
+
+    pub fn get(&self, key: ChunkKey) -> Option<&Chunk> {
+        self.chunks.get(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    // Chunks requested or generated but not handed out yet
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Drop every chunk, e.g. after the height source changed, the next update regenerates them
+    pub fn clear(&mut self) -> Vec<ChunkKey> {
+        self.pending.clear();
+        self.ready.clear();
+        self.chunks.drain().map(|(key, _)| key).collect()
+    }
+
+    // Replace the height source, all loaded chunks are dropped and returned so their GPU buffers can be freed
+    pub fn set_map(&mut self, source: SharedHeightSource) -> Vec<ChunkKey> {
+        // Chunks still being generated from the old map are discarded when they arrive
+        self.generation = self.generation.wrapping_add(1);
+        self.generator.source = source;
+        self.clear()
+    }
+
+    // Classify the vertices of every chunk into biomes, regenerates all chunks like set_map
+    pub fn set_biomes(&mut self, biomes: Option<Arc<BiomeMap>>) -> Vec<ChunkKey> {
+        self.generation = self.generation.wrapping_add(1);
+        self.generator.biomes = biomes;
+        self.clear()
+    }
+
+    // Scatter objects on every chunk, filtered by the biome map when there is one, regenerates all chunks like set_map
+    pub fn set_scatter(&mut self, scatter: Option<Arc<Scatter>>) -> Vec<ChunkKey> {
+        self.generation = self.generation.wrapping_add(1);
+        self.generator.scatter = scatter;
+        self.clear()
+    }
+}
+
+impl Drop for ChunkManager {
+    fn drop(&mut self) {
+        // Closing the job channel makes every worker leave its loop
+        self.wanted.lock().unwrap().clear();
+        self.job_sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
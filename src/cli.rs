@@ -0,0 +1,466 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::biomes::{Biome, BiomeMap};
+use crate::scatter::Scatter;
+use crate::config::{Config, FilterConfig, MaskShape};
+use crate::export::heightmap::{export_heightmap, HeightmapFormat, HeightmapMetadata};
+use crate::export::mesh::{export_mesh, ExportMesh, MeshExportOptions, MeshFormat};
+use crate::export::rivers::export_rivers;
+use crate::filters::{Filter, Pipeline};
+use crate::fractal_noise::FractalNoise;
+use crate::height_grid::HeightGrid;
+use crate::height_source::SharedHeightSource;
+use crate::heightmap::Heightmap;
+use crate::hydrology::{RiverNetwork, Rivers};
+use crate::perlin_noise::PerlinMap;
+use crate::shaping::{Mask, ShapedTerrain};
+
+// Command Line
+// Parses the arguments into a command and its options, and runs the commands that need no window
+/* Example:
+terrain_generator export-heightmap --seed 42 --plain 1024x1024 --scale 0.02 --octaves 5 -o terrain.png
+terrain_generator export-mesh --seed 42 --plain 256x256 --base 0.3 -o terrain.stl
+terrain_generator stats --map-size 20x20 --seed 7
+terrain_generator export-rivers --config rivers.toml -o rivers.geojson --mask water.png
+terrain_generator --config my_terrain.toml --seed 42 (same as terrain_generator view ..., flags override the config file)
+*/
+
+// Loaded when no --config is given, if it exists
+pub const DEFAULT_CONFIG_PATH: &str = "resources/terrain.toml";
+
+pub const USAGE: &str = "\
+Usage: terrain_generator [COMMAND] [OPTIONS]
+
+Commands:
+  view                 Interactive viewer (default)
+  export-heightmap     Write the terrain as a heightmap image or raw file (needs --output)
+  export-mesh          Write the terrain as a mesh (needs --output)
+  export-rivers        Write the rivers as GeoJSON polylines (needs --output and a [rivers] config section)
+  stats                Print height statistics of the terrain
+
+Terrain options (override the config file):
+  --config <PATH>      Terrain config file (default: resources/terrain.toml if it exists)
+  --seed <N>           Seed for the gradients (default: random)
+  --map-size <WxH>     Use a bounded W x H gradient map instead of unbounded hashed gradients
+  --octaves <N>        Number of noise octaves (default: 1)
+  --heightmap <PATH>   Use a heightmap image instead of noise
+  --plain <WxH>        Vertices of the plain or exported grid (default: 10x10)
+  --scale <F>          Distance between vertices in noise units (default: 0.2)
+  --origin <X,Y>       World position of the first vertex (default: 0,0)
+
+Export options:
+  -o, --output <PATH>  Output file, the format is taken from the extension unless --format is given
+  --format <NAME>      png8, png16, pgm8, pgm16, pfm, f32, u16 (heightmaps), obj, stl, gltf, glb (meshes)
+  --horizontal-scale <F>  Mesh scale along x and y (default: 1)
+  --vertical-scale <F> Mesh scale of the heights (default: 1)
+  --base <F>           Close the mesh with a solid base this far below its lowest point
+  --mask <PATH>        export-rivers: also write the river and lake mask as a heightmap
+
+Viewer options:
+  --window <WxH>       Window size (default: 1200x720)
+
+  -h, --help           Show this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    View,
+    ExportHeightmap,
+    ExportMesh,
+    ExportRivers,
+    Stats,
+    Help
+}
+
+#[derive(Debug, Clone)]
+pub struct CliOptions {
+    pub command: Command,
+    pub config_path: Option<PathBuf>,
+    // Terrain flags in the order given, applied on top of the config file (again after every live reload)
+    overrides: Vec<(String, String)>,
+    // Used when neither the config nor the flags give a seed, so reloading keeps the same terrain
+    random_seed: u64,
+    pub config: Config,
+    pub output: Option<PathBuf>,
+    pub format: Option<String>,
+    pub horizontal_scale: f32,
+    pub vertical_scale: f32,
+    pub base: Option<f32>,
+    pub mask: Option<PathBuf>
+}
+
+impl CliOptions {
+    // Arguments without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliOptions, String> {
+        let mut options = CliOptions {
+            command: Command::View,
+            config_path: None,
+            overrides: Vec::new(),
+            random_seed: rand::random(),
+            config: Config::default(),
+            output: None,
+            format: None,
+            horizontal_scale: 1.0,
+            vertical_scale: 1.0,
+            base: None,
+            mask: None
+        };
+        let mut args = args.into_iter().peekable();
+
+        // The command is optional and must come first
+        if let Some(first) = args.peek() {
+            let command = match first.as_str() {
+                "view" => Some(Command::View),
+                "export-heightmap" => Some(Command::ExportHeightmap),
+                "export-mesh" => Some(Command::ExportMesh),
+                "export-rivers" => Some(Command::ExportRivers),
+                "stats" => Some(Command::Stats),
+                "help" => Some(Command::Help),
+                other if !other.starts_with('-') => return Err(format!("unknown command '{}'", other)),
+                _ => None
+            };
+            if let Some(command) = command {
+                options.command = command;
+                args.next();
+            }
+        }
+
+        while let Some(arg) = args.next() {
+            // --name=value and --name value are both accepted
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None)
+            };
+
+            if name == "-h" || name == "--help" {
+                options.command = Command::Help;
+                continue;
+            }
+
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return Err(format!("missing value for '{}'", name))
+            };
+
+            match name.as_str() {
+                "--config" => options.config_path = Some(PathBuf::from(value)),
+                "-o" | "--output" => options.output = Some(PathBuf::from(value)),
+                "--format" => options.format = Some(value),
+                "--horizontal-scale" => options.horizontal_scale = parse_value(&name, &value)?,
+                "--vertical-scale" => options.vertical_scale = parse_value(&name, &value)?,
+                "--base" => options.base = Some(parse_value(&name, &value)?),
+                "--mask" => options.mask = Some(PathBuf::from(value)),
+                _ => {
+                    // Checked here so a typo is reported before any work is done
+                    apply_override(&mut Config::default(), &name, &value)?;
+                    options.overrides.push((name, value));
+                }
+            }
+        }
+
+        if matches!(options.command, Command::ExportHeightmap | Command::ExportMesh | Command::ExportRivers) && options.output.is_none() {
+            return Err("an output path is needed (--output)".to_string());
+        }
+
+        if options.command != Command::Help {
+            options.config = options.load_config()?;
+        }
+        Ok(options)
+    }
+
+    // Path of the config file in use, if any
+    pub fn get_config_path(&self) -> Option<&Path> {
+        match &self.config_path {
+            Some(path) => Some(path),
+            None => Some(Path::new(DEFAULT_CONFIG_PATH)).filter(|path| path.exists())
+        }
+    }
+
+    // Read the config file (or the defaults) and apply the flags on top, also used to reload the file
+    pub fn load_config(&self) -> Result<Config, String> {
+        let mut config = match self.get_config_path() {
+            Some(path) => Config::load(path)?,
+            None => Config::default()
+        };
+
+        for (name, value) in self.overrides.iter() {
+            apply_override(&mut config, name, value)?;
+        }
+        config.noise.seed.get_or_insert(self.random_seed);
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+// Terrain flags write into the matching config keys
+fn apply_override(config: &mut Config, name: &str, value: &str) -> Result<(), String> {
+    match name {
+        "--seed" => config.noise.seed = Some(parse_value(name, value)?),
+        "--map-size" => config.noise.map_size = Some(parse_size(name, value)?.into()),
+        "--octaves" => config.noise.octaves = parse_value(name, value)?,
+        "--heightmap" => config.terrain.heightmap = Some(PathBuf::from(value)),
+        "--plain" => config.terrain.plain = parse_size(name, value)?.into(),
+        "--scale" => config.terrain.scale = parse_value(name, value)?,
+        "--origin" => config.terrain.origin = parse_pair(name, value, ',')?.into(),
+        "--window" => (config.window.width, config.window.height) = parse_size(name, value)?,
+        _ => return Err(format!("unknown option '{}'", name))
+    }
+
+    Ok(())
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("invalid value '{}' for '{}'", value, name))
+}
+
+fn parse_pair<T: std::str::FromStr>(name: &str, value: &str, separator: char) -> Result<(T, T), String> {
+    match value.split_once(separator) {
+        Some((a, b)) => Ok((parse_value(name, a)?, parse_value(name, b)?)),
+        None => Err(format!("invalid value '{}' for '{}', expected A{}B", value, name, separator))
+    }
+}
+
+// 1024x768
+fn parse_size<T: std::str::FromStr>(name: &str, value: &str) -> Result<(T, T), String> {
+    parse_pair(name, &value.to_ascii_lowercase(), 'x')
+}
+
+// Noise map from the config
+pub fn build_perlin_map(config: &Config) -> PerlinMap {
+    let seed = config.noise.seed.unwrap_or_else(rand::random);
+    let mut perlin_map = PerlinMap::new();
+
+    match config.noise.map_size {
+        Some([w, h]) => perlin_map.generate_seeded_vec_map(w, h, seed),
+        None => perlin_map.set_hashed(seed)
+    }
+    perlin_map.set_seamless(config.noise.seamless);
+
+    perlin_map
+}
+
+// Terrain heights and the rivers on them, built from the config
+pub struct Terrain {
+    pub source: SharedHeightSource,
+    pub rivers: Option<RiverNetwork>
+}
+
+// Terrain heights from the config: a heightmap image, or octaves of the noise map
+// With filters or rivers the plain is sampled once, processed and used as a heightmap of that region
+pub fn build_terrain(config: &Config) -> Result<Terrain, String> {
+    let source: SharedHeightSource = match &config.terrain.heightmap {
+        Some(path) => {
+            let heightmap = Heightmap::load(path).map_err(|e| format!("failed to load heightmap: {}", e))?;
+            Arc::new(heightmap)
+        },
+        None => {
+            let mut noise = FractalNoise::new(build_perlin_map(config), config.noise.octaves);
+            noise.set_lacunarity(config.noise.lacunarity);
+            noise.set_persistence(config.noise.persistence);
+            Arc::new(noise)
+        }
+    };
+
+    let source = if config.shape.is_active() { Arc::new(build_shaped_terrain(config, source)) } else { source };
+    if config.filters.is_empty() && config.rivers.is_none() {
+        return Ok(Terrain { source, rivers: None });
+    }
+
+    let terrain = &config.terrain;
+    let mut grid = build_pipeline(config).run(source.as_ref(), terrain.origin[0], terrain.origin[1], terrain.scale, terrain.plain[0] as usize, terrain.plain[1] as usize);
+    let rivers = config.rivers.as_ref().map(|rivers| {
+        Rivers {
+            method: rivers.method,
+            threshold: rivers.threshold,
+            depth: rivers.depth,
+            max_depth: rivers.max_depth,
+            sea_level: Some(terrain.sea_level),
+            min_lake_depth: rivers.min_lake_depth
+        }.generate(&mut grid)
+    });
+
+    let mut heightmap = Heightmap::from_grid(&grid);
+    heightmap.set_seed(source.seed());
+    Ok(Terrain { source: Arc::new(heightmap), rivers })
+}
+
+pub fn build_source(config: &Config) -> Result<SharedHeightSource, String> {
+    build_terrain(config).map(|terrain| terrain.source)
+}
+
+// Climate from the [biomes] section, seeded like the terrain so a random seed gives matching biomes
+pub fn build_biome_map(config: &Config, terrain: &SharedHeightSource) -> Option<BiomeMap> {
+    let biomes = config.biomes.as_ref()?;
+    let seed = terrain.seed().or(config.noise.seed).unwrap_or_else(rand::random);
+    Some(BiomeMap::new(seed, biomes.settings(config.terrain.sea_level)))
+}
+
+// Object layers from the [[scatter]] sections, none without any
+pub fn build_scatter(config: &Config, terrain: &SharedHeightSource) -> Option<Scatter> {
+    if config.scatter.is_empty() {
+        return None;
+    }
+
+    let seed = terrain.seed().or(config.noise.seed).unwrap_or_else(rand::random);
+    Some(Scatter::new(seed.wrapping_add(4), config.scatter.iter().map(|layer| layer.layer()).collect()))
+}
+
+// Coastlines from the [shape] section, the masks are placed on the plain
+pub fn build_shaped_terrain(config: &Config, detail: SharedHeightSource) -> ShapedTerrain {
+    let (shape, terrain) = (&config.shape, &config.terrain);
+    let region_min = terrain.origin;
+    let region_max = [
+        terrain.origin[0] + (terrain.plain[0] - 1) as f32 * terrain.scale,
+        terrain.origin[1] + (terrain.plain[1] - 1) as f32 * terrain.scale
+    ];
+
+    let mut shaped = ShapedTerrain::new(detail, region_min, region_max);
+    shaped.set_ocean_floor(shape.ocean_floor);
+    match shape.mask {
+        MaskShape::None => {},
+        MaskShape::Radial => shaped.add_mask(Mask::Radial { radius: shape.radius, falloff: shape.falloff }),
+        MaskShape::Square => shaped.add_mask(Mask::Square { radius: shape.radius, falloff: shape.falloff }),
+        MaskShape::Edge => {
+            let half_size = (region_max[0] - region_min[0]).min(region_max[1] - region_min[1]) / 2.0;
+            shaped.add_mask(Mask::EdgeDistance { distance: shape.falloff * half_size });
+        }
+    }
+
+    if let Some(size) = shape.continent_size {
+        // Its own seed, otherwise the continents would follow the detail noise
+        let mut perlin_map = PerlinMap::new();
+        perlin_map.set_hashed(config.noise.seed.unwrap_or_else(rand::random).wrapping_add(1));
+        let noise = FractalNoise::new(perlin_map, 3);
+        shaped.add_mask(Mask::Continent { noise: Arc::new(noise), size, threshold: shape.continent_threshold, blend: shape.continent_blend });
+    }
+
+    if let Some(ratio) = shape.land_ratio {
+        let reached = shaped.fit_land_ratio(ratio, terrain.sea_level);
+        if (reached - ratio).abs() > 0.01 {
+            eprintln!("Note: shape.land_ratio {} is out of reach with these masks, {:.2} of the plain is land", ratio, reached);
+        }
+    }
+
+    shaped
+}
+
+// Filter pipeline from the [[filters]] list
+pub fn build_pipeline(config: &Config) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    for filter in config.filters.iter().cloned() {
+        match filter {
+            FilterConfig::Blur { sigma } => pipeline.push(Filter::Blur { sigma }),
+            FilterConfig::Sharpen { sigma, amount } => pipeline.push(Filter::Sharpen { sigma, amount }),
+            FilterConfig::Curve { points } => pipeline.push(Filter::Curve(points)),
+            FilterConfig::Clamp { min, max } => pipeline.push(Filter::Clamp { min, max }),
+            FilterConfig::Plateau { height, strength } => pipeline.push(Filter::Plateau { height, strength }),
+            FilterConfig::Island { radius, falloff, floor } => pipeline.push(Filter::Island { radius, falloff, floor }),
+            FilterConfig::HydraulicErosion(erosion) => pipeline.push(erosion),
+            FilterConfig::ThermalErosion(erosion) => pipeline.push(erosion)
+        }
+    }
+
+    pipeline
+}
+
+fn sample_grid(config: &Config, source: &SharedHeightSource) -> HeightGrid {
+    let terrain = &config.terrain;
+    HeightGrid::sample(source.as_ref(), terrain.origin[0], terrain.origin[1], terrain.scale, terrain.plain[0] as usize, terrain.plain[1] as usize)
+}
+
+fn output_format<T>(options: &CliOptions, path: &Path, from_name: fn(&str) -> Option<T>, from_path: fn(&Path) -> Option<T>) -> Result<T, String> {
+    match &options.format {
+        Some(name) => from_name(name).ok_or_else(|| format!("unknown format '{}'", name)),
+        None => from_path(path).ok_or_else(|| format!("cannot tell the format of '{}', use --format", path.display()))
+    }
+}
+
+// Runs a command that needs no window
+pub fn run_headless(options: &CliOptions) -> Result<(), String> {
+    let config = &options.config;
+    // Filters run while building the source, so the stats time includes them
+    let start = Instant::now();
+    let Terrain { source, rivers } = build_terrain(config)?;
+
+    match options.command {
+        Command::ExportHeightmap => {
+            let path = options.output.as_deref().expect("validated");
+            let format = output_format(options, path, HeightmapFormat::from_name, HeightmapFormat::from_path)?;
+
+            let grid = sample_grid(config, &source);
+            let metadata = HeightmapMetadata::from_grid(&grid, source.seed());
+            export_heightmap(&grid, path, format, &metadata).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("Wrote {} ({}x{}, {})", path.display(), grid.width(), grid.height(), format.name());
+        },
+        Command::ExportMesh => {
+            let path = options.output.as_deref().expect("validated");
+            let format = output_format(options, path, MeshFormat::from_name, MeshFormat::from_path)?;
+
+            let grid = sample_grid(config, &source);
+            let mesh_options = MeshExportOptions {
+                horizontal_scale: options.horizontal_scale,
+                vertical_scale: options.vertical_scale,
+                base: options.base,
+                ..MeshExportOptions::default()
+            };
+            let mesh = ExportMesh::from_grid(&grid, &mesh_options);
+            export_mesh(&mesh, path, format).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("Wrote {} ({} vertices, {} triangles)", path.display(), mesh.positions.len(), mesh.triangle_count());
+        },
+        Command::ExportRivers => {
+            let path = options.output.as_deref().expect("validated");
+            let network = rivers.ok_or("export-rivers needs a [rivers] section in the config file")?;
+
+            export_rivers(&network, path).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("Wrote {} ({} rivers)", path.display(), network.polylines.len());
+
+            if let Some(mask_path) = options.mask.as_deref() {
+                let format = output_format(options, mask_path, HeightmapFormat::from_name, HeightmapFormat::from_path)?;
+                let metadata = HeightmapMetadata::from_grid(&network.water_mask, source.seed());
+                export_heightmap(&network.water_mask, mask_path, format, &metadata).map_err(|e| format!("failed to write {}: {}", mask_path.display(), e))?;
+                println!("Wrote {} ({}x{}, {})", mask_path.display(), network.water_mask.width(), network.water_mask.height(), format.name());
+            }
+        },
+        Command::Stats => {
+            let grid = sample_grid(config, &source);
+            let elapsed = start.elapsed();
+
+            let (min, max) = grid.min_max();
+            let count = grid.data().len() as f64;
+            let mean = grid.data().iter().map(|&h| h as f64).sum::<f64>() / count;
+            let variance = grid.data().iter().map(|&h| (h as f64 - mean).powi(2)).sum::<f64>() / count;
+
+            match source.seed() {
+                Some(seed) => println!("Seed:       {}", seed),
+                None => println!("Seed:       none")
+            }
+            println!("Grid:       {}x{} vertices, scale {}, origin ({}, {})", grid.width(), grid.height(), config.terrain.scale, config.terrain.origin[0], config.terrain.origin[1]);
+            println!("Triangles:  {}", 2 * (grid.width() - 1) * (grid.height() - 1));
+            println!("Height:     min {:.4}, max {:.4}, mean {:.4}, std dev {:.4}", min, max, mean, variance.sqrt());
+            let land = grid.data().iter().filter(|&&h| h > config.terrain.sea_level).count() as f64 / count;
+            println!("Land:       {:.1}% above sea level {}", land * 100.0, config.terrain.sea_level);
+            if let Some(biomes) = build_biome_map(config, &source) {
+                // Share of the grid per primary biome, most common first
+                let mut counts = [0usize; Biome::ALL.len()];
+                let (origin_x, origin_y) = grid.origin();
+                for y in 0 .. grid.height() {
+                    for x in 0 .. grid.width() {
+                        let (wx, wy) = (origin_x + x as f32 * grid.step(), origin_y + y as f32 * grid.step());
+                        counts[biomes.classify(wx, wy, grid.get(x, y)).primary.id() as usize] += 1;
+                    }
+                }
+
+                let mut shares: Vec<(Biome, usize)> = Biome::ALL.iter().copied().zip(counts).filter(|(_, n)| *n > 0).collect();
+                shares.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+                let shares: Vec<String> = shares.iter().map(|(biome, n)| format!("{} {:.1}%", biome.name(), *n as f64 / count * 100.0)).collect();
+                println!("Biomes:     {}", shares.join(", "));
+            }
+            println!("Generated:  {:.2} ms", elapsed.as_secs_f64() * 1000.0);
+        },
+        Command::View | Command::Help => unreachable!("not a headless command")
+    }
+
+    Ok(())
+}
@@ -0,0 +1,611 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use crate::erosion::{HydraulicErosion, ThermalErosion};
+use crate::biomes::{Biome, BiomeSettings};
+use crate::hydrology::FlowMethod;
+use crate::scatter::ScatterLayer;
+
+#[cfg(feature = "viewer")]
+use crate::graphics::input::{InputAction, InputBindings};
+
+// Terrain Config
+// Everything that describes the generator (noise, plain, filters, colours, water, window, controls) in one TOML file,
+// so tuning the terrain needs no recompiling
+// Every key is optional and falls back to the default, unknown keys and invalid values are errors naming the key
+/* Example:
+let config = Config::load("resources/terrain.toml")?;
+
+let mut watcher = ConfigWatcher::new("resources/terrain.toml");
+if watcher.changed() {
+    // reload the file
+}
+*/
+
+// Largest number of colour ramp stops the terrain shader accepts
+pub const MAX_COLOR_STOPS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub live_reload: bool, // Reload the file in the viewer when it changes
+    pub noise: NoiseConfig,
+    pub terrain: TerrainConfig,
+    pub shape: ShapeConfig,
+    pub filters: Vec<FilterConfig>, // Applied in order to the terrain plain
+    pub rivers: Option<RiversConfig>, // Rivers and lakes on the plain after the filters, none without the section
+    pub color_ramp: Vec<ColorStop>,
+    pub biomes: Option<BiomesConfig>, // Colours the terrain by biome instead of the ramp, none without the section
+    pub scatter: Vec<ScatterConfig>, // Object layers placed on the terrain by the viewer
+    pub water: WaterConfig,
+    pub window: WindowConfig,
+    // action = "binding, binding", the same names as controls.cfg, replacing the bindings of that action
+    pub controls: BTreeMap<String, String>
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoiseConfig {
+    pub seed: Option<u64>, // Random when missing
+    pub map_size: Option<[i32; 2]>, // Bounded stored gradient map instead of unbounded hashed gradients
+    pub seamless: bool, // Wrap the stored map instead of ending at its edge
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub persistence: f32
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerrainConfig {
+    pub heightmap: Option<PathBuf>, // Image used instead of noise
+    pub plain: [i32; 2], // Vertices of the plain (width, height)
+    pub scale: f32, // Distance between vertices in noise units
+    pub origin: [f32; 2],
+    pub sea_level: f32
+}
+
+// Coastlines: masks placed on the plain and a continent noise layer, see shaping::ShapedTerrain
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShapeConfig {
+    pub mask: MaskShape,
+    pub radius: f32, // Land within this fraction of half the plain
+    pub falloff: f32, // Width of the coast as a fraction of half the plain
+    pub continent_size: Option<f32>, // Size of the continent features in noise units, no continent layer when missing
+    pub continent_threshold: f32, // Higher gives less land
+    pub continent_blend: f32,
+    pub land_ratio: Option<f32>, // Share of the plain above the sea level, the heights are offset to reach it
+    pub ocean_floor: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskShape {
+    None,
+    Radial,
+    Square,
+    Edge // Distance to the edge of the plain, falloff is measured from the edge
+}
+
+// One entry of the [[filters]] list, picked by type = "blur", "sharpen", ... (see filters::Filter)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FilterConfig {
+    Blur { sigma: f32 },
+    Sharpen { sigma: f32, amount: f32 },
+    Curve { points: Vec<[f32; 2]> },
+    Clamp { min: f32, max: f32 },
+    Plateau { height: f32, strength: f32 },
+    Island { radius: f32, falloff: f32, floor: f32 },
+    HydraulicErosion(HydraulicErosion),
+    ThermalErosion(ThermalErosion)
+}
+
+// See hydrology::Rivers, the rivers end in the sea at terrain.sea_level
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiversConfig {
+    pub method: FlowMethod, // "d8" or "d_infinity"
+    pub threshold: f32, // Cells draining into a cell before it is a river
+    pub depth: f32,
+    pub max_depth: f32,
+    pub min_lake_depth: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorStop {
+    pub height: f32,
+    pub color: [f32; 3] // RGB, 0.0 .. 1.0
+}
+
+// See biomes::BiomeSettings, the sea level is terrain.sea_level
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BiomesConfig {
+    pub size: f32,
+    pub temperature_offset: f32,
+    pub lapse_rate: f32,
+    pub latitude_gradient: f32,
+    pub equator: f32,
+    pub beach_height: f32,
+    pub snow_temperature: f32,
+    pub blend: f32,
+    pub colors: BTreeMap<String, [f32; 3]> // Biome name = colour, replacing its default colour
+}
+
+// One [[scatter]] layer, see scatter::ScatterLayer
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScatterConfig {
+    pub model: PathBuf, // glTF model, y up
+    pub color: [f32; 3],
+    pub spacing: f32,
+    pub density: f32,
+    pub biomes: Vec<String>, // Biome names, needs a [biomes] section
+    pub min_height: f32,
+    pub max_height: f32,
+    pub max_slope: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub max_level: u32
+}
+
+// Transparent water surface at terrain.sea_level
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaterConfig {
+    pub enabled: bool,
+    pub shallow_color: [f32; 3],
+    pub deep_color: [f32; 3],
+    pub deep_depth: f32, // Depth where the water reaches the deep colour
+    pub opacity: f32, // Of shallow water, deep water is opaque
+    pub foam_width: f32, // Height band around the shoreline drawn as foam
+    pub impassable_depth: Option<f32> // Deeper water blocks walking in first person, passable when missing
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub swap_interval: i32, // 0 = no vsync, 1 = vsync, 2 = every second refresh, -1 = adaptive
+    pub update_rate: f64 // Fixed simulation steps per second
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            live_reload: false,
+            noise: NoiseConfig::default(),
+            terrain: TerrainConfig::default(),
+            shape: ShapeConfig::default(),
+            filters: Vec::new(),
+            rivers: None,
+            // The same colours as the old height formula (0, z / 2 + 1 / 2, z / 5 + 4 / 5)
+            color_ramp: vec![
+                ColorStop { height: -1.0, color: [0.0, 0.0, 0.6] },
+                ColorStop { height: 1.0, color: [0.0, 1.0, 1.0] }
+            ],
+            biomes: None,
+            scatter: Vec::new(),
+            water: WaterConfig::default(),
+            window: WindowConfig::default(),
+            controls: BTreeMap::new()
+        }
+    }
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        NoiseConfig {
+            seed: None,
+            map_size: None,
+            seamless: false,
+            octaves: 1,
+            lacunarity: 2.0,
+            persistence: 0.5
+        }
+    }
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        TerrainConfig {
+            heightmap: None,
+            plain: [10, 10],
+            scale: 0.2,
+            origin: [0.0, 0.0],
+            sea_level: 0.0
+        }
+    }
+}
+
+impl Default for ShapeConfig {
+    fn default() -> Self {
+        ShapeConfig {
+            mask: MaskShape::None,
+            radius: 0.5,
+            falloff: 0.4,
+            continent_size: None,
+            continent_threshold: 0.0,
+            continent_blend: 0.1,
+            land_ratio: None,
+            ocean_floor: -0.6
+        }
+    }
+}
+
+impl ShapeConfig {
+    pub fn is_active(&self) -> bool {
+        self.mask != MaskShape::None || self.continent_size.is_some() || self.land_ratio.is_some()
+    }
+}
+
+impl Default for RiversConfig {
+    fn default() -> Self {
+        RiversConfig {
+            method: FlowMethod::D8,
+            threshold: 100.0,
+            depth: 0.005,
+            max_depth: 0.03,
+            min_lake_depth: 0.002
+        }
+    }
+}
+
+impl Default for BiomesConfig {
+    fn default() -> Self {
+        let settings = BiomeSettings::default();
+        BiomesConfig {
+            size: settings.size,
+            temperature_offset: settings.temperature_offset,
+            lapse_rate: settings.lapse_rate,
+            latitude_gradient: settings.latitude_gradient,
+            equator: settings.equator,
+            beach_height: settings.beach_height,
+            snow_temperature: settings.snow_temperature,
+            blend: settings.blend,
+            colors: BTreeMap::new()
+        }
+    }
+}
+
+impl BiomesConfig {
+    pub fn settings(&self, sea_level: f32) -> BiomeSettings {
+        BiomeSettings {
+            size: self.size,
+            temperature_offset: self.temperature_offset,
+            lapse_rate: self.lapse_rate,
+            latitude_gradient: self.latitude_gradient,
+            equator: self.equator,
+            sea_level,
+            beach_height: self.beach_height,
+            snow_temperature: self.snow_temperature,
+            blend: self.blend
+        }
+    }
+
+    // Colour of every biome by id, with the configured colours applied
+    pub fn colors(&self) -> Vec<[f32; 3]> {
+        Biome::ALL.iter().map(|biome| self.colors.get(biome.name()).copied().unwrap_or(biome.color())).collect()
+    }
+}
+
+impl Default for ScatterConfig {
+    fn default() -> Self {
+        let layer = ScatterLayer::default();
+        ScatterConfig {
+            model: PathBuf::new(),
+            color: [0.4, 0.4, 0.4],
+            spacing: layer.spacing,
+            density: layer.density,
+            biomes: Vec::new(),
+            min_height: layer.min_height,
+            max_height: layer.max_height,
+            max_slope: layer.max_slope,
+            min_scale: layer.min_scale,
+            max_scale: layer.max_scale,
+            max_level: layer.max_level
+        }
+    }
+}
+
+impl ScatterConfig {
+    // Unknown biome names are rejected by validate
+    pub fn layer(&self) -> ScatterLayer {
+        ScatterLayer {
+            spacing: self.spacing,
+            density: self.density,
+            biomes: self.biomes.iter().filter_map(|name| Biome::from_name(name)).collect(),
+            min_height: self.min_height,
+            max_height: self.max_height,
+            max_slope: self.max_slope,
+            min_scale: self.min_scale,
+            max_scale: self.max_scale,
+            max_level: self.max_level
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.model.as_os_str().is_empty() {
+            return Err("model: missing".to_string());
+        }
+        if !(self.spacing > 0.0 && self.spacing.is_finite()) {
+            return Err("spacing: must be positive".to_string());
+        }
+        if !(0.0 ..= 1.0).contains(&self.density) {
+            return Err("density: must be between 0.0 and 1.0".to_string());
+        }
+        if let Some(name) = self.biomes.iter().find(|name| Biome::from_name(name).is_none()) {
+            return Err(format!("biomes: unknown biome {}", name));
+        }
+        if !(self.min_height <= self.max_height && self.min_scale <= self.max_scale && self.min_scale > 0.0 && self.max_scale.is_finite()) {
+            return Err("min_height, max_height, min_scale, max_scale: min must not be above max, scales must be positive".to_string());
+        }
+        if self.color.iter().any(|c| !(0.0 ..= 1.0).contains(c)) {
+            return Err("color: components must be between 0.0 and 1.0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for WaterConfig {
+    fn default() -> Self {
+        WaterConfig {
+            enabled: false,
+            shallow_color: [0.2, 0.6, 0.7],
+            deep_color: [0.0, 0.1, 0.3],
+            deep_depth: 0.3,
+            opacity: 0.5,
+            foam_width: 0.01,
+            impassable_depth: None
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 1200,
+            height: 720,
+            swap_interval: 1,
+            update_rate: 120.0
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, String> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Config::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(source: &str) -> Result<Config, String> {
+        // toml errors already name the key and show the line
+        let config: Config = toml::from_str(source).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Checks the values serde cannot, errors start with the path of the bad key
+    pub fn validate(&self) -> Result<(), String> {
+        let noise = &self.noise;
+        if noise.octaves == 0 {
+            return Err("noise.octaves: must be at least 1".to_string());
+        }
+        if !(noise.lacunarity > 0.0 && noise.lacunarity.is_finite()) {
+            return Err("noise.lacunarity: must be positive".to_string());
+        }
+        if !(noise.persistence > 0.0 && noise.persistence.is_finite()) {
+            return Err("noise.persistence: must be positive".to_string());
+        }
+        if let Some([w, h]) = noise.map_size {
+            if w < 1 || h < 1 {
+                return Err("noise.map_size: must be at least [1, 1]".to_string());
+            }
+        }
+
+        let terrain = &self.terrain;
+        if terrain.plain[0] < 2 || terrain.plain[1] < 2 {
+            return Err("terrain.plain: needs at least [2, 2] vertices".to_string());
+        }
+        if !(terrain.scale > 0.0 && terrain.scale.is_finite()) {
+            return Err("terrain.scale: must be positive".to_string());
+        }
+        if terrain.origin.iter().any(|v| !v.is_finite()) || !terrain.sea_level.is_finite() {
+            return Err("terrain: origin and sea_level must be finite numbers".to_string());
+        }
+
+        let shape = &self.shape;
+        if !(shape.radius >= 0.0 && shape.radius.is_finite()) {
+            return Err("shape.radius: must not be negative".to_string());
+        }
+        if !(shape.falloff > 0.0 && shape.falloff.is_finite()) {
+            return Err("shape.falloff: must be positive".to_string());
+        }
+        if shape.continent_size.is_some_and(|size| !(size > 0.0 && size.is_finite())) {
+            return Err("shape.continent_size: must be positive".to_string());
+        }
+        if !(shape.continent_blend >= 0.0 && shape.continent_blend.is_finite()) {
+            return Err("shape.continent_blend: must not be negative".to_string());
+        }
+        if !shape.continent_threshold.is_finite() || !shape.ocean_floor.is_finite() {
+            return Err("shape: continent_threshold and ocean_floor must be finite numbers".to_string());
+        }
+        if shape.land_ratio.is_some_and(|ratio| !(0.0 ..= 1.0).contains(&ratio)) {
+            return Err("shape.land_ratio: must be between 0.0 and 1.0".to_string());
+        }
+        if shape.is_active() && shape.ocean_floor >= terrain.sea_level {
+            return Err("shape.ocean_floor: must be below terrain.sea_level".to_string());
+        }
+
+        for (i, filter) in self.filters.iter().enumerate() {
+            filter.validate().map_err(|e| format!("filters[{}].{}", i, e))?;
+        }
+
+        if let Some(rivers) = &self.rivers {
+            if !(rivers.threshold >= 1.0 && rivers.threshold.is_finite()) {
+                return Err("rivers.threshold: must be at least 1".to_string());
+            }
+            if !(rivers.depth >= 0.0 && rivers.max_depth >= rivers.depth && rivers.max_depth.is_finite()) {
+                return Err("rivers.max_depth: must not be below rivers.depth, which must not be negative".to_string());
+            }
+            if !(rivers.min_lake_depth >= 0.0 && rivers.min_lake_depth.is_finite()) {
+                return Err("rivers.min_lake_depth: must not be negative".to_string());
+            }
+        }
+
+        if self.color_ramp.is_empty() || self.color_ramp.len() > MAX_COLOR_STOPS {
+            return Err(format!("color_ramp: needs 1 to {} stops", MAX_COLOR_STOPS));
+        }
+        for (i, stop) in self.color_ramp.iter().enumerate() {
+            if !stop.height.is_finite() {
+                return Err(format!("color_ramp[{}].height: must be a finite number", i));
+            }
+            if i > 0 && stop.height < self.color_ramp[i - 1].height {
+                return Err(format!("color_ramp[{}].height: stops must be sorted by height", i));
+            }
+            if stop.color.iter().any(|c| !(0.0 ..= 1.0).contains(c)) {
+                return Err(format!("color_ramp[{}].color: components must be between 0.0 and 1.0", i));
+            }
+        }
+
+        if let Some(biomes) = &self.biomes {
+            if !(biomes.size > 0.0 && biomes.size.is_finite()) {
+                return Err("biomes.size: must be positive".to_string());
+            }
+            if !(biomes.blend >= 0.0 && biomes.blend.is_finite() && biomes.beach_height >= 0.0 && biomes.beach_height.is_finite()) {
+                return Err("biomes: blend and beach_height must not be negative".to_string());
+            }
+            if [biomes.temperature_offset, biomes.lapse_rate, biomes.latitude_gradient, biomes.equator].iter().any(|v| !v.is_finite()) {
+                return Err("biomes: temperature_offset, lapse_rate, latitude_gradient and equator must be finite numbers".to_string());
+            }
+            if !(0.0 .. 1.0).contains(&biomes.snow_temperature) {
+                return Err("biomes.snow_temperature: must be between 0.0 and 1.0".to_string());
+            }
+            for (name, color) in biomes.colors.iter() {
+                if Biome::from_name(name).is_none() {
+                    return Err(format!("biomes.colors.{}: unknown biome", name));
+                }
+                if color.iter().any(|c| !(0.0 ..= 1.0).contains(c)) {
+                    return Err(format!("biomes.colors.{}: components must be between 0.0 and 1.0", name));
+                }
+            }
+        }
+
+        for (i, layer) in self.scatter.iter().enumerate() {
+            layer.validate().map_err(|e| format!("scatter[{}].{}", i, e))?;
+        }
+
+        let water = &self.water;
+        for (name, color) in [("shallow_color", water.shallow_color), ("deep_color", water.deep_color)] {
+            if color.iter().any(|c| !(0.0 ..= 1.0).contains(c)) {
+                return Err(format!("water.{}: components must be between 0.0 and 1.0", name));
+            }
+        }
+        if !(water.deep_depth > 0.0 && water.deep_depth.is_finite()) {
+            return Err("water.deep_depth: must be positive".to_string());
+        }
+        if !(0.0 ..= 1.0).contains(&water.opacity) {
+            return Err("water.opacity: must be between 0.0 and 1.0".to_string());
+        }
+        if !(water.foam_width >= 0.0 && water.foam_width.is_finite()) {
+            return Err("water.foam_width: must not be negative".to_string());
+        }
+        if water.impassable_depth.is_some_and(|depth| !(depth >= 0.0 && depth.is_finite())) {
+            return Err("water.impassable_depth: must not be negative".to_string());
+        }
+
+        let window = &self.window;
+        if window.width == 0 || window.height == 0 {
+            return Err("window: width and height must not be 0".to_string());
+        }
+        if window.swap_interval < -1 {
+            return Err("window.swap_interval: must be -1 (adaptive), 0 (off) or a number of refreshes".to_string());
+        }
+        if !(window.update_rate > 0.0 && window.update_rate.is_finite()) {
+            return Err("window.update_rate: must be positive".to_string());
+        }
+
+        // Binding names come from GLFW, without the viewer there is nothing to bind and the section is not checked
+        #[cfg(feature = "viewer")]
+        self.apply_controls(InputBindings::new())?;
+
+        Ok(())
+    }
+
+    // Bindings with the actions listed in [controls] replaced
+    #[cfg(feature = "viewer")]
+    pub fn apply_controls(&self, mut bindings: InputBindings) -> Result<InputBindings, String> {
+        for (name, values) in self.controls.iter() {
+            let action = InputAction::from_name(name).ok_or_else(|| format!("controls.{}: unknown action", name))?;
+            bindings.bind(action, InputBindings::parse_binding_list(values).map_err(|e| format!("controls.{}: {}", name, e))?);
+        }
+
+        Ok(bindings)
+    }
+}
+
+impl FilterConfig {
+    // Errors start with the key inside the filter
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            FilterConfig::Blur { sigma } | FilterConfig::Sharpen { sigma, .. } if !(*sigma > 0.0 && sigma.is_finite()) => Err("sigma: must be positive".to_string()),
+            FilterConfig::Sharpen { amount, .. } if !amount.is_finite() => Err("amount: must be a finite number".to_string()),
+            FilterConfig::Curve { points } if points.is_empty() => Err("points: needs at least 1 point".to_string()),
+            FilterConfig::Curve { points } if points.iter().flatten().any(|v| !v.is_finite()) => Err("points: must be finite numbers".to_string()),
+            FilterConfig::Curve { points } if points.windows(2).any(|p| p[1][0] < p[0][0]) => Err("points: must be sorted by input height".to_string()),
+            FilterConfig::Clamp { min, max } if !(min.is_finite() && max.is_finite() && min <= max) => Err("min, max: must be finite numbers, min not above max".to_string()),
+            FilterConfig::Plateau { height, .. } if !height.is_finite() => Err("height: must be a finite number".to_string()),
+            FilterConfig::Plateau { strength, .. } if !(0.0 ..= 1.0).contains(strength) => Err("strength: must be between 0.0 and 1.0".to_string()),
+            FilterConfig::Island { radius, .. } if !(*radius >= 0.0 && radius.is_finite()) => Err("radius: must not be negative".to_string()),
+            FilterConfig::Island { falloff, .. } if !(*falloff > 0.0 && falloff.is_finite()) => Err("falloff: must be positive".to_string()),
+            FilterConfig::Island { floor, .. } if !floor.is_finite() => Err("floor: must be a finite number".to_string()),
+            FilterConfig::HydraulicErosion(erosion) if !(0.0 ..= 1.0).contains(&erosion.inertia) => Err("inertia: must be between 0.0 and 1.0".to_string()),
+            FilterConfig::HydraulicErosion(erosion) if !(0.0 ..= 1.0).contains(&erosion.evaporation) => Err("evaporation: must be between 0.0 and 1.0".to_string()),
+            FilterConfig::HydraulicErosion(erosion) if !(0.0 ..= 1.0).contains(&erosion.deposition) || !(0.0 ..= 1.0).contains(&erosion.erosion) => Err("deposition, erosion: must be between 0.0 and 1.0".to_string()),
+            FilterConfig::HydraulicErosion(erosion) if [erosion.capacity, erosion.min_slope, erosion.gravity].iter().any(|v| !(*v >= 0.0 && v.is_finite())) => Err("capacity, min_slope, gravity: must not be negative".to_string()),
+            FilterConfig::ThermalErosion(erosion) if !(erosion.rate > 0.0 && erosion.rate.is_finite()) => Err("rate: must be positive".to_string()),
+            FilterConfig::ThermalErosion(erosion) if !(0.0 .. 90.0).contains(&erosion.talus_angle) => Err("talus_angle: must be between 0 and 90 degrees".to_string()),
+            _ => Ok(())
+        }
+    }
+}
+
+// Polls the modification time of a file, cheap enough to call about once a second
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>
+}
+
+impl ConfigWatcher {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path);
+        ConfigWatcher { path, modified }
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    // True once after every change of the file
+    pub fn changed(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        if modified != self.modified {
+            self.modified = modified;
+            modified.is_some()
+        } else {
+            false
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
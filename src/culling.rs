@@ -0,0 +1,151 @@
+// Frustum Culling
+// Axis-aligned bounding boxes and the six planes of a view frustum, so draws that are entirely off screen can be skipped
+// The frustum is taken from the matrix that maps world positions to clip space, with or without perspective
+/* Example:
+let frustum = Frustum::from_matrix(&(projection * view).into());
+
+let bounds = Aabb::from_points(&vertices).translated([chunk.origin_x, chunk.origin_y, 0.0]);
+if frustum.intersects(&bounds) {
+    mesh.draw();
+}
+
+// Counting the draws of a frame
+let mut stats = CullStats::default();
+for (mesh, bounds) in meshes.iter() {
+    if stats.test(&frustum, bounds) {
+        mesh.draw();
+    }
+}
+println!("{} drawn, {} culled", stats.drawn, stats.culled);
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3]
+}
+
+impl Aabb {
+    pub fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        Aabb { min, max }
+    }
+
+    // Contains nothing, grows to the first point added
+    pub fn empty() -> Self {
+        Aabb { min: [f32::INFINITY; 3], max: [f32::NEG_INFINITY; 3] }
+    }
+
+    // Bounds of xyz triples
+    pub fn from_points(points: &[f32]) -> Self {
+        let mut aabb = Aabb::empty();
+        for point in points.chunks_exact(3) {
+            aabb.add_point([point[0], point[1], point[2]]);
+        }
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0 .. 3).any(|i| self.min[i] > self.max[i])
+    }
+
+    pub fn add_point(&mut self, point: [f32; 3]) {
+        for (i, p) in point.into_iter().enumerate() {
+            self.min[i] = self.min[i].min(p);
+            self.max[i] = self.max[i].max(p);
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: [0, 1, 2].map(|i| self.min[i].min(other.min[i])),
+            max: [0, 1, 2].map(|i| self.max[i].max(other.max[i]))
+        }
+    }
+
+    pub fn translated(&self, offset: [f32; 3]) -> Aabb {
+        Aabb {
+            min: [0, 1, 2].map(|i| self.min[i] + offset[i]),
+            max: [0, 1, 2].map(|i| self.max[i] + offset[i])
+        }
+    }
+
+    // Grown by margin on every side
+    pub fn expanded(&self, margin: [f32; 3]) -> Aabb {
+        Aabb {
+            min: [0, 1, 2].map(|i| self.min[i] - margin[i]),
+            max: [0, 1, 2].map(|i| self.max[i] + margin[i])
+        }
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        [0, 1, 2].map(|i| (self.min[i] + self.max[i]) / 2.0)
+    }
+}
+
+// Planes as (a, b, c, d) with the inside where a x + b y + c z + d >= 0: left, right, bottom, top, near, far
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [[f32; 4]; 6]
+}
+
+impl Frustum {
+    // Column-major matrix as used by OpenGL and cgmath (matrix[column][row])
+    pub fn from_matrix(matrix: &[[f32; 4]; 4]) -> Self {
+        let row = |r: usize| [matrix[0][r], matrix[1][r], matrix[2][r], matrix[3][r]];
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let add = |a: [f32; 4], b: [f32; 4]| [0, 1, 2, 3].map(|i| a[i] + b[i]);
+        let sub = |a: [f32; 4], b: [f32; 4]| [0, 1, 2, 3].map(|i| a[i] - b[i]);
+
+        // Clip space keeps -w <= x, y, z <= w
+        Frustum { planes: [add(w, x), sub(w, x), add(w, y), sub(w, y), add(w, z), sub(w, z)] }
+    }
+
+    pub fn contains_point(&self, point: [f32; 3]) -> bool {
+        self.planes.iter().all(|plane| distance(plane, point) >= 0.0)
+    }
+
+    // False only when the box is entirely outside one of the planes, boxes near a corner can pass while off screen
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane normal
+            let corner = [0, 1, 2].map(|i| if plane[i] >= 0.0 { aabb.max[i] } else { aabb.min[i] });
+            distance(plane, corner) >= 0.0
+        })
+    }
+}
+
+// How many draws a frustum let through and how many it skipped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize
+}
+
+impl CullStats {
+    // Tests the bounds against the frustum and counts the result, true when the draw should happen
+    pub fn test(&mut self, frustum: &Frustum, bounds: &Aabb) -> bool {
+        let visible = frustum.intersects(bounds);
+        if visible {
+            self.drawn += 1;
+        } else {
+            self.culled += 1;
+        }
+        visible
+    }
+
+    pub fn total(&self) -> usize {
+        self.drawn + self.culled
+    }
+
+    pub fn reset(&mut self) {
+        *self = CullStats::default();
+    }
+}
+
+fn distance(plane: &[f32; 4], point: [f32; 3]) -> f32 {
+    plane[0] * point[0] + plane[1] * point[1] + plane[2] * point[2] + plane[3]
+}
@@ -0,0 +1,275 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use std::f32::consts::SQRT_2;
+
+use crate::height_grid::{GridPass, HeightGrid};
+
+// Hydraulic Erosion
+// Simulates rain droplets running down the terrain one after another: a droplet picks up sediment while it speeds downhill
+// and drops it where it slows down or the ground flattens, carving gullies and leaving sediment fans at their ends
+// Works in grid cells, so the result depends on the resolution of the grid and not on its world placement
+// The droplets start at positions drawn from the seed, the same grid and parameters always give the same result
+/* Example:
+let mut grid = HeightGrid::sample(&perlin_map, 0.0, 0.0, 0.02, 512, 512);
+
+let mut erosion = HydraulicErosion::default();
+erosion.iterations = 100_000;
+erosion.erode(&mut grid);
+
+let (vertices, indices, _) = generate_mesh(0.02, 512, 512, 0.0, 0.0, &Heightmap::from_grid(&grid));
+*/
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HydraulicErosion {
+    pub seed: u64,
+    pub iterations: u32, // Number of droplets
+    pub max_lifetime: u32, // Steps before a droplet is dropped
+    pub inertia: f32, // 0.0 = droplets follow the slope exactly, 1.0 = they never change direction
+    pub capacity: f32, // Sediment a droplet can carry per unit of slope, speed and water
+    pub min_slope: f32, // Slope used for the capacity on flat ground, keeps droplets eroding there
+    pub deposition: f32, // Fraction of the excess sediment dropped per step
+    pub erosion: f32, // Fraction of the missing capacity taken from the ground per step
+    pub evaporation: f32, // Fraction of the water lost per step
+    pub gravity: f32,
+    pub radius: u32 // Droplets erode the cells within this many cells, wider gives smoother gullies
+}
+
+impl Default for HydraulicErosion {
+    fn default() -> Self {
+        HydraulicErosion {
+            seed: 0,
+            iterations: 50_000,
+            max_lifetime: 30,
+            inertia: 0.05,
+            capacity: 4.0,
+            min_slope: 0.01,
+            deposition: 0.3,
+            erosion: 0.3,
+            evaporation: 0.01,
+            gravity: 4.0,
+            radius: 3
+        }
+    }
+}
+
+impl HydraulicErosion {
+    pub fn erode(&self, grid: &mut HeightGrid) {
+        let (width, height) = (grid.width(), grid.height());
+        if width < 2 || height < 2 {
+            return;
+        }
+
+        let brush = Brush::new(self.radius as i32);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let map = grid.data_mut();
+
+        for _ in 0 .. self.iterations {
+            let mut x = rng.gen_range(0.0 .. (width - 1) as f32);
+            let mut y = rng.gen_range(0.0 .. (height - 1) as f32);
+            let (mut dir_x, mut dir_y) = (0.0, 0.0);
+            let mut speed = 1.0;
+            let mut water = 1.0;
+            let mut sediment = 0.0;
+
+            for _ in 0 .. self.max_lifetime {
+                let (cell_x, cell_y) = (x as usize, y as usize);
+                let (fx, fy) = (x - cell_x as f32, y - cell_y as f32);
+                let (h, grad_x, grad_y) = height_and_gradient(map, width, x, y);
+
+                // Blend the previous direction with the downhill direction
+                dir_x = dir_x * self.inertia - grad_x * (1.0 - self.inertia);
+                dir_y = dir_y * self.inertia - grad_y * (1.0 - self.inertia);
+                let length = (dir_x * dir_x + dir_y * dir_y).sqrt();
+                if length == 0.0 {
+                    // Perfectly flat, nowhere to go
+                    break;
+                }
+                dir_x /= length;
+                dir_y /= length;
+                x += dir_x;
+                y += dir_y;
+
+                if x < 0.0 || y < 0.0 || x >= (width - 1) as f32 || y >= (height - 1) as f32 {
+                    // The sediment leaves the grid with the droplet
+                    break;
+                }
+
+                let delta_h = height_and_gradient(map, width, x, y).0 - h;
+                let capacity = (-delta_h).max(self.min_slope) * speed * water * self.capacity;
+
+                if sediment > capacity || delta_h > 0.0 {
+                    // Uphill: fill the pit behind the droplet, at most up to the new height
+                    // Otherwise drop part of what is above the capacity
+                    let amount = if delta_h > 0.0 { delta_h.min(sediment) } else { (sediment - capacity) * self.deposition };
+                    sediment -= amount;
+
+                    // Onto the four corners of the cell the droplet left, weighted by how close it was
+                    let i = cell_y * width + cell_x;
+                    map[i] += amount * (1.0 - fx) * (1.0 - fy);
+                    map[i + 1] += amount * fx * (1.0 - fy);
+                    map[i + width] += amount * (1.0 - fx) * fy;
+                    map[i + width + 1] += amount * fx * fy;
+                } else {
+                    // Never dig deeper than the height difference, that would leave a hole behind the droplet
+                    let amount = ((capacity - sediment) * self.erosion).min(-delta_h);
+                    sediment += brush.erode(map, width, height, cell_x, cell_y, amount);
+                }
+
+                speed = (speed * speed - delta_h * self.gravity).max(0.0).sqrt();
+                water *= 1.0 - self.evaporation;
+            }
+        }
+    }
+}
+
+impl GridPass for HydraulicErosion {
+    fn apply(&self, grid: &mut HeightGrid) {
+        self.erode(grid);
+    }
+}
+
+// Thermal Erosion
+// Weathering: wherever the slope to a neighbour is steeper than the talus angle, material slides down to it
+// until the slope settles at that angle, softening cliffs into scree
+// Material only moves between cells, the total height of the grid stays the same
+/* Example:
+let thermal = ThermalErosion { talus_angle: 35.0, ..Default::default() };
+grid.apply(&HydraulicErosion::default()).apply(&thermal);
+*/
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThermalErosion {
+    pub iterations: u32,
+    pub talus_angle: f32, // Steepest stable slope in degrees, measured with the step of the grid
+    pub rate: f32 // Fraction of the excess moved per iteration, 0.5 settles without overshooting
+}
+
+impl Default for ThermalErosion {
+    fn default() -> Self {
+        ThermalErosion {
+            iterations: 50,
+            talus_angle: 30.0,
+            rate: 0.5
+        }
+    }
+}
+
+impl ThermalErosion {
+    pub fn erode(&self, grid: &mut HeightGrid) {
+        let (width, height) = (grid.width(), grid.height());
+        // Largest stable height difference to a side neighbour, diagonal neighbours are further away
+        let talus = self.talus_angle.to_radians().tan() * grid.step();
+        let neighbours = [
+            (-1, 0, talus), (1, 0, talus), (0, -1, talus), (0, 1, talus),
+            (-1, -1, talus * SQRT_2), (1, -1, talus * SQRT_2), (-1, 1, talus * SQRT_2), (1, 1, talus * SQRT_2)
+        ];
+
+        // Every cell moves material based on the heights before the iteration, so the order of the cells does not matter
+        let mut delta = vec![0.0; width * height];
+        for _ in 0 .. self.iterations {
+            let map = grid.data();
+
+            for y in 0 .. height {
+                for x in 0 .. width {
+                    let h = map[y * width + x];
+                    let inside = |dx: i32, dy: i32| {
+                        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                        nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height
+                    };
+                    let excess = |dx: i32, dy: i32, talus: f32| h - map[(y as i32 + dy) as usize * width + (x as i32 + dx) as usize] - talus;
+
+                    // Split the material between the neighbours that are too low, the lowest gets the most
+                    let (total, max) = neighbours.iter()
+                        .filter(|(dx, dy, _)| inside(*dx, *dy))
+                        .map(|&(dx, dy, talus)| excess(dx, dy, talus))
+                        .filter(|e| *e > 0.0)
+                        .fold((0.0, 0.0f32), |(total, max), e| (total + e, max.max(e)));
+                    if total == 0.0 {
+                        continue;
+                    }
+
+                    // Half the largest excess levels the steepest pair, more would swap which side is higher
+                    let amount = max * self.rate.min(0.5);
+                    delta[y * width + x] -= amount;
+                    for &(dx, dy, talus) in neighbours.iter().filter(|(dx, dy, _)| inside(*dx, *dy)) {
+                        let e = excess(dx, dy, talus);
+                        if e > 0.0 {
+                            delta[(y as i32 + dy) as usize * width + (x as i32 + dx) as usize] += amount * e / total;
+                        }
+                    }
+                }
+            }
+
+            for (h, d) in grid.data_mut().iter_mut().zip(delta.iter_mut()) {
+                *h += *d;
+                *d = 0.0;
+            }
+        }
+    }
+}
+
+impl GridPass for ThermalErosion {
+    fn apply(&self, grid: &mut HeightGrid) {
+        self.erode(grid);
+    }
+}
+
+// Bilinear height and gradient at a point inside the grid
+fn height_and_gradient(map: &[f32], width: usize, x: f32, y: f32) -> (f32, f32, f32) {
+    let (cell_x, cell_y) = (x as usize, y as usize);
+    let (fx, fy) = (x - cell_x as f32, y - cell_y as f32);
+
+    let i = cell_y * width + cell_x;
+    let (nw, ne, sw, se) = (map[i], map[i + 1], map[i + width], map[i + width + 1]);
+
+    let grad_x = (ne - nw) * (1.0 - fy) + (se - sw) * fy;
+    let grad_y = (sw - nw) * (1.0 - fx) + (se - ne) * fx;
+    let h = nw * (1.0 - fx) * (1.0 - fy) + ne * fx * (1.0 - fy) + sw * (1.0 - fx) * fy + se * fx * fy;
+
+    (h, grad_x, grad_y)
+}
+
+// Cell offsets within the erosion radius and their weights, falling off linearly with the distance
+struct Brush {
+    offsets: Vec<(i32, i32, f32)>
+}
+
+impl Brush {
+    fn new(radius: i32) -> Self {
+        let radius = radius.max(1);
+        let mut offsets = Vec::new();
+
+        for dy in -radius ..= radius {
+            for dx in -radius ..= radius {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance < radius as f32 {
+                    offsets.push((dx, dy, 1.0 - distance / radius as f32));
+                }
+            }
+        }
+
+        Brush { offsets }
+    }
+
+    // Takes up to amount from the cells around (x, y), returns what was taken
+    // Cells outside the grid are skipped and the weights renormalized, so the edges erode as much as the middle
+    fn erode(&self, map: &mut [f32], width: usize, height: usize, x: usize, y: usize, amount: f32) -> f32 {
+        let inside = |dx: i32, dy: i32| {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height
+        };
+        let total: f32 = self.offsets.iter().filter(|(dx, dy, _)| inside(*dx, *dy)).map(|(_, _, w)| w).sum();
+
+        let mut taken = 0.0;
+        for &(dx, dy, weight) in self.offsets.iter().filter(|(dx, dy, _)| inside(*dx, *dy)) {
+            let i = (y as i32 + dy) as usize * width + (x as i32 + dx) as usize;
+            map[i] -= amount * weight / total;
+            taken += amount * weight / total;
+        }
+
+        taken
+    }
+}
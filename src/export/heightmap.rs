@@ -0,0 +1,215 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::height_grid::HeightGrid;
+
+// Heightmap Exporter
+// Writes a height grid as an image or raw file for other tools, plus a JSON sidecar (<file>.json)
+// recording where the heights came from and how to turn the stored values back into heights
+/* Example:
+let grid = HeightGrid::sample(&perlin_map, 0.0, 0.0, 0.05, 1024, 1024);
+let metadata = HeightmapMetadata::from_grid(&grid, perlin_map.seed());
+export_heightmap(&grid, "terrain.png", HeightmapFormat::Png16, &metadata)?;
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeightmapFormat {
+    Png8, // 8-bit grayscale PNG, heights normalized to 0 .. 255
+    Png16, // 16-bit grayscale PNG, heights normalized to 0 .. 65535
+    Pgm8, // Binary PGM (P5) with maxval 255
+    Pgm16, // Binary PGM (P5) with maxval 65535, big-endian samples
+    Pfm, // Grayscale PFM (Pf), little-endian f32 heights, rows stored bottom to top
+    RawF32, // Little-endian f32 heights, row-major, no header
+    RawU16 // Little-endian u16 normalized heights, row-major, no header
+}
+
+impl HeightmapFormat {
+    pub fn from_name(name: &str) -> Option<HeightmapFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "png8" => Some(HeightmapFormat::Png8),
+            "png" | "png16" => Some(HeightmapFormat::Png16),
+            "pgm8" => Some(HeightmapFormat::Pgm8),
+            "pgm" | "pgm16" => Some(HeightmapFormat::Pgm16),
+            "pfm" => Some(HeightmapFormat::Pfm),
+            "f32" | "raw" | "rawf32" => Some(HeightmapFormat::RawF32),
+            "u16" | "rawu16" => Some(HeightmapFormat::RawU16),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeightmapFormat::Png8 => "png8",
+            HeightmapFormat::Png16 => "png16",
+            HeightmapFormat::Pgm8 => "pgm8",
+            HeightmapFormat::Pgm16 => "pgm16",
+            HeightmapFormat::Pfm => "pfm",
+            HeightmapFormat::RawF32 => "f32",
+            HeightmapFormat::RawU16 => "u16"
+        }
+    }
+
+    // Guess the format from a file extension (.png, .pgm, .pfm, .f32/.raw, .u16/.r16)
+    pub fn from_path(path: &Path) -> Option<HeightmapFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "r16" => Some(HeightmapFormat::RawU16),
+            other => HeightmapFormat::from_name(other)
+        }
+    }
+
+    // Integer formats store heights normalized to the height range of the grid
+    pub fn is_normalized(&self) -> bool {
+        !matches!(self, HeightmapFormat::Pfm | HeightmapFormat::RawF32)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightmapMetadata {
+    pub seed: Option<u64>,
+    pub scale: f32, // World distance between two samples
+    pub offset_x: f32, // World position of the first sample
+    pub offset_y: f32,
+    pub min_height: f32,
+    pub max_height: f32
+}
+
+impl HeightmapMetadata {
+    pub fn from_grid(grid: &HeightGrid, seed: Option<u64>) -> Self {
+        let (offset_x, offset_y) = grid.origin();
+        let (min_height, max_height) = grid.min_max();
+
+        HeightmapMetadata {
+            seed,
+            scale: grid.step(),
+            offset_x,
+            offset_y,
+            min_height,
+            max_height
+        }
+    }
+}
+
+// Write the grid in the chosen format and the JSON sidecar next to it
+pub fn export_heightmap(grid: &HeightGrid, path: impl AsRef<Path>, format: HeightmapFormat, metadata: &HeightmapMetadata) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    match format {
+        HeightmapFormat::Png8 => write_png(&mut writer, grid, metadata, png::BitDepth::Eight)?,
+        HeightmapFormat::Png16 => write_png(&mut writer, grid, metadata, png::BitDepth::Sixteen)?,
+        HeightmapFormat::Pgm8 => write_pgm(&mut writer, grid, metadata, 255)?,
+        HeightmapFormat::Pgm16 => write_pgm(&mut writer, grid, metadata, 65535)?,
+        HeightmapFormat::Pfm => write_pfm(&mut writer, grid)?,
+        HeightmapFormat::RawF32 => {
+            for h in grid.data() {
+                writer.write_all(&h.to_le_bytes())?;
+            }
+        },
+        HeightmapFormat::RawU16 => {
+            for h in grid.data() {
+                writer.write_all(&(normalize(*h, metadata, 65535) as u16).to_le_bytes())?;
+            }
+        }
+    }
+    writer.flush()?;
+
+    std::fs::write(sidecar_path(path), sidecar_json(grid, format, metadata))
+}
+
+// terrain.png -> terrain.png.json
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+// Map a height into 0 .. max_value using the metadata height range
+fn normalize(height: f32, metadata: &HeightmapMetadata, max_value: u32) -> u32 {
+    let range = metadata.max_height - metadata.min_height;
+    if range <= 0.0 {
+        return 0;
+    }
+
+    let t = ((height - metadata.min_height) / range).clamp(0.0, 1.0);
+    (t * max_value as f32).round() as u32
+}
+
+fn write_png(writer: &mut impl Write, grid: &HeightGrid, metadata: &HeightmapMetadata, depth: png::BitDepth) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(writer, grid.width() as u32, grid.height() as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(depth);
+
+    let mut bytes = Vec::new();
+    for h in grid.data() {
+        match depth {
+            png::BitDepth::Sixteen => bytes.extend_from_slice(&(normalize(*h, metadata, 65535) as u16).to_be_bytes()),
+            _ => bytes.push(normalize(*h, metadata, 255) as u8)
+        }
+    }
+
+    let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+    png_writer.write_image_data(&bytes).map_err(io::Error::other)?;
+    // Dropping the writer would finish it too, but swallow a failed write of the end chunk
+    png_writer.finish().map_err(io::Error::other)
+}
+
+fn write_pgm(writer: &mut impl Write, grid: &HeightGrid, metadata: &HeightmapMetadata, max_value: u32) -> io::Result<()> {
+    write!(writer, "P5\n{} {}\n{}\n", grid.width(), grid.height(), max_value)?;
+
+    for h in grid.data() {
+        let value = normalize(*h, metadata, max_value);
+        if max_value > 255 {
+            writer.write_all(&(value as u16).to_be_bytes())?;
+        } else {
+            writer.write_all(&[value as u8])?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_pfm(writer: &mut impl Write, grid: &HeightGrid) -> io::Result<()> {
+    // Negative scale marks little-endian data
+    write!(writer, "Pf\n{} {}\n-1.0\n", grid.width(), grid.height())?;
+
+    // PFM stores the bottom row first
+    for row in (0 .. grid.height()).rev() {
+        for col in 0 .. grid.width() {
+            writer.write_all(&grid.get(col, row).to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn sidecar_json(grid: &HeightGrid, format: HeightmapFormat, metadata: &HeightmapMetadata) -> String {
+    let seed = match metadata.seed {
+        Some(seed) => seed.to_string(),
+        None => "null".to_string()
+    };
+
+    format!(
+        "{{\n  \"format\": \"{}\",\n  \"width\": {},\n  \"height\": {},\n  \"normalized\": {},\n  \"seed\": {},\n  \"scale\": {},\n  \"offset_x\": {},\n  \"offset_y\": {},\n  \"min_height\": {},\n  \"max_height\": {}\n}}\n",
+        format.name(),
+        grid.width(),
+        grid.height(),
+        format.is_normalized(),
+        seed,
+        json_number(metadata.scale),
+        json_number(metadata.offset_x),
+        json_number(metadata.offset_y),
+        json_number(metadata.min_height),
+        json_number(metadata.max_height)
+    )
+}
+
+// JSON has no NaN or infinity
+pub(crate) fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        format!("{:?}", value)
+    } else {
+        "null".to_string()
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::functions::generate_grid_indices;
+use crate::height_grid::HeightGrid;
+
+// Mesh Exporter
+// Writes terrain meshes (the vertices and indices from generate_mesh, or a height grid) as Wavefront OBJ,
+// binary STL for 3D printing, or glTF 2.0 (.gltf with an embedded buffer, or binary .glb)
+// Terrain is Z-up, OBJ and glTF are written Y-up as those formats expect, STL stays Z-up like slicers expect
+/* Example:
+let (vertices, indices, _) = generate_mesh(scale, plain_h, plain_w, x, y, &perlin_map);
+let options = MeshExportOptions { vertical_scale: 2.0, base: Some(0.2), ..MeshExportOptions::default() };
+let mesh = ExportMesh::from_vertices(&vertices, &indices, &options);
+export_mesh(&mesh, "terrain.glb", MeshFormat::Glb)?;
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    Obj,
+    Stl, // Binary STL
+    Gltf, // glTF JSON with the buffer embedded as a data URI
+    Glb // Binary glTF
+}
+
+impl MeshFormat {
+    pub fn from_name(name: &str) -> Option<MeshFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "obj" => Some(MeshFormat::Obj),
+            "stl" => Some(MeshFormat::Stl),
+            "gltf" => Some(MeshFormat::Gltf),
+            "glb" => Some(MeshFormat::Glb),
+            _ => None
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<MeshFormat> {
+        MeshFormat::from_name(path.extension()?.to_str()?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MeshExportOptions {
+    pub horizontal_scale: f32, // Multiplies x and y, e.g. to turn noise units into metres or millimetres
+    pub vertical_scale: f32, // Multiplies heights, values above the horizontal scale exaggerate the relief
+    pub normals: bool,
+    pub uvs: bool, // 0.0 .. 1.0 across the terrain, v = 0.0 on the first row
+    // Closes the mesh with walls and a flat bottom this far below the lowest point, so it can be 3D printed
+    pub base: Option<f32>
+}
+
+impl Default for MeshExportOptions {
+    fn default() -> Self {
+        MeshExportOptions {
+            horizontal_scale: 1.0,
+            vertical_scale: 1.0,
+            normals: true,
+            uvs: true,
+            base: None
+        }
+    }
+}
+
+// Triangle mesh ready to be written, Z-up with counter-clockwise triangles facing outwards
+#[derive(Debug, Clone, Default)]
+pub struct ExportMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>, // Empty when disabled
+    pub uvs: Vec<[f32; 2]>, // Empty when disabled
+    pub indices: Vec<u32>
+}
+
+impl ExportMesh {
+    // vertices holds x, y, z per vertex and indices 3 per triangle, as returned by generate_mesh
+    pub fn from_vertices(vertices: &[f32], indices: &[i32], options: &MeshExportOptions) -> Self {
+        let positions: Vec<[f32; 3]> = vertices
+            .chunks_exact(3)
+            .map(|v| [v[0] * options.horizontal_scale, v[1] * options.horizontal_scale, v[2] * options.vertical_scale])
+            .collect();
+        let mut indices: Vec<u32> = indices.iter().map(|&i| i as u32).collect();
+
+        // generate_mesh winds its triangles clockwise seen from above, flip them so the surface faces up
+        let facing: f32 = indices.chunks_exact(3).map(|t| face_normal(&positions, t)[2]).sum();
+        if facing < 0.0 {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        let normals = if options.normals { smooth_normals(&positions, &indices) } else { Vec::new() };
+        let uvs = if options.uvs { planar_uvs(&positions) } else { Vec::new() };
+
+        let mut mesh = ExportMesh { positions, normals, uvs, indices };
+        if let Some(depth) = options.base {
+            mesh.add_base(depth * options.vertical_scale);
+        }
+        mesh
+    }
+
+    // Grid vertices at their world positions (origin + index * step)
+    pub fn from_grid(grid: &HeightGrid, options: &MeshExportOptions) -> Self {
+        let (origin_x, origin_y) = grid.origin();
+        let mut vertices = Vec::with_capacity(grid.data().len() * 3);
+        for row in 0 .. grid.height() {
+            for col in 0 .. grid.width() {
+                vertices.extend_from_slice(&[origin_x + col as f32 * grid.step(), origin_y + row as f32 * grid.step(), grid.get(col, row)]);
+            }
+        }
+
+        let (indices, _) = generate_grid_indices(grid.height() as i32, grid.width() as i32);
+        ExportMesh::from_vertices(&vertices, &indices, options)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // Walls down from every boundary edge and a flat bottom depth below the lowest point
+    // The bottom is a fan around the centre of the outline, which works for any outline that is convex like a grid
+    // Walls and bottom get their own vertices so their flat normals do not bend the surface normals
+    fn add_base(&mut self, depth: f32) {
+        // An edge only one triangle uses, kept in that triangle's direction (interior on the left seen from above)
+        let edges: HashSet<(u32, u32)> = self.indices.chunks_exact(3).flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]).collect();
+        let boundary: Vec<(u32, u32)> = edges.iter().copied().filter(|&(a, b)| !edges.contains(&(b, a))).collect();
+        if boundary.is_empty() {
+            return;
+        }
+
+        let (min, _) = bounds(&self.positions);
+        let base_z = min[2] - depth.abs();
+        let down = [0.0, 0.0, -1.0];
+
+        let count = boundary.len() as f32;
+        let mut center = [0.0, 0.0];
+        let mut center_uv = [0.0, 0.0];
+        for &(a, _) in boundary.iter() {
+            let (p, uv) = (self.positions[a as usize], self.uv(a));
+            center = [center[0] + p[0] / count, center[1] + p[1] / count];
+            center_uv = [center_uv[0] + uv[0] / count, center_uv[1] + uv[1] / count];
+        }
+        let bottom_center = self.push_vertex([center[0], center[1], base_z], down, center_uv);
+
+        for (a, b) in boundary {
+            let (top_a, top_b) = (self.positions[a as usize], self.positions[b as usize]);
+            let (uv_a, uv_b) = (self.uv(a), self.uv(b));
+            // Outwards is to the right of a -> b
+            let outward = normalize([top_b[1] - top_a[1], top_a[0] - top_b[0], 0.0]);
+
+            let wall_a = self.push_vertex(top_a, outward, uv_a);
+            let wall_b = self.push_vertex(top_b, outward, uv_b);
+            let wall_bottom_a = self.push_vertex([top_a[0], top_a[1], base_z], outward, uv_a);
+            let wall_bottom_b = self.push_vertex([top_b[0], top_b[1], base_z], outward, uv_b);
+            self.indices.extend_from_slice(&[wall_a, wall_bottom_a, wall_bottom_b, wall_a, wall_bottom_b, wall_b]);
+
+            let bottom_a = self.push_vertex([top_a[0], top_a[1], base_z], down, uv_a);
+            let bottom_b = self.push_vertex([top_b[0], top_b[1], base_z], down, uv_b);
+            self.indices.extend_from_slice(&[bottom_center, bottom_b, bottom_a]);
+        }
+    }
+
+    fn uv(&self, i: u32) -> [f32; 2] {
+        self.uvs.get(i as usize).copied().unwrap_or([0.0; 2])
+    }
+
+    // Normal and uv are only kept when the mesh has them
+    fn push_vertex(&mut self, position: [f32; 3], normal: [f32; 3], uv: [f32; 2]) -> u32 {
+        if !self.normals.is_empty() {
+            self.normals.push(normal);
+        }
+        if !self.uvs.is_empty() {
+            self.uvs.push(uv);
+        }
+        self.positions.push(position);
+        self.positions.len() as u32 - 1
+    }
+}
+
+pub fn export_mesh(mesh: &ExportMesh, path: impl AsRef<Path>, format: MeshFormat) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    match format {
+        MeshFormat::Obj => write_obj(&mut writer, mesh)?,
+        MeshFormat::Stl => write_stl(&mut writer, mesh)?,
+        MeshFormat::Gltf => write_gltf(&mut writer, mesh)?,
+        MeshFormat::Glb => write_glb(&mut writer, mesh)?
+    }
+
+    writer.flush()
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length > 0.0 {
+        [v[0] / length, v[1] / length, v[2] / length]
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+// Not normalized, the length is twice the triangle area
+fn face_normal(positions: &[[f32; 3]], triangle: &[u32]) -> [f32; 3] {
+    let (a, b, c) = (positions[triangle[0] as usize], positions[triangle[1] as usize], positions[triangle[2] as usize]);
+    cross(sub(b, a), sub(c, a))
+}
+
+// Area weighted average of the normals of the triangles around each vertex
+fn smooth_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = vec![[0.0; 3]; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let n = face_normal(positions, triangle);
+        for &i in triangle {
+            let normal = &mut normals[i as usize];
+            normal[0] += n[0];
+            normal[1] += n[1];
+            normal[2] += n[2];
+        }
+    }
+
+    normals.into_iter().map(normalize).collect()
+}
+
+fn planar_uvs(positions: &[[f32; 3]]) -> Vec<[f32; 2]> {
+    let (min, max) = bounds(positions);
+    let width = (max[0] - min[0]).max(f32::EPSILON);
+    let depth = (max[1] - min[1]).max(f32::EPSILON);
+
+    positions.iter().map(|p| [(p[0] - min[0]) / width, (p[1] - min[1]) / depth]).collect()
+}
+
+fn bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+    for p in positions {
+        for axis in 0 .. 3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+
+    (min, max)
+}
+
+// Z-up terrain to the Y-up axes of OBJ and glTF (a rotation, so winding and handedness are kept)
+fn y_up(p: [f32; 3]) -> [f32; 3] {
+    [p[0], p[2], -p[1]]
+}
+
+fn write_obj(writer: &mut impl Write, mesh: &ExportMesh) -> io::Result<()> {
+    writeln!(writer, "# Terrain Generator mesh, {} vertices, {} triangles", mesh.positions.len(), mesh.triangle_count())?;
+
+    for p in mesh.positions.iter().map(|&p| y_up(p)) {
+        writeln!(writer, "v {} {} {}", p[0], p[1], p[2])?;
+    }
+    // OBJ texture coordinates start at the bottom left
+    for uv in mesh.uvs.iter() {
+        writeln!(writer, "vt {} {}", uv[0], 1.0 - uv[1])?;
+    }
+    for n in mesh.normals.iter().map(|&n| y_up(n)) {
+        writeln!(writer, "vn {} {} {}", n[0], n[1], n[2])?;
+    }
+
+    // OBJ indices start at 1
+    let (has_uvs, has_normals) = (!mesh.uvs.is_empty(), !mesh.normals.is_empty());
+    for triangle in mesh.indices.chunks_exact(3) {
+        write!(writer, "f")?;
+        for &i in triangle {
+            let i = i + 1;
+            match (has_uvs, has_normals) {
+                (true, true) => write!(writer, " {}/{}/{}", i, i, i)?,
+                (true, false) => write!(writer, " {}/{}", i, i)?,
+                (false, true) => write!(writer, " {}//{}", i, i)?,
+                (false, false) => write!(writer, " {}", i)?
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn write_stl(writer: &mut impl Write, mesh: &ExportMesh) -> io::Result<()> {
+    let mut header = [0u8; 80];
+    let title = b"Terrain Generator binary STL";
+    header[.. title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.triangle_count() as u32).to_le_bytes())?;
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let normal = normalize(face_normal(&mesh.positions, triangle));
+        for value in normal.iter().chain(triangle.iter().flat_map(|&i| mesh.positions[i as usize].iter())) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        // Attribute byte count, unused
+        writer.write_all(&[0, 0])?;
+    }
+
+    Ok(())
+}
+
+// Binary buffer shared by .gltf and .glb, and the JSON describing it (without the buffer uri)
+fn gltf_parts(mesh: &ExportMesh, uri: Option<&str>) -> (String, Vec<u8>) {
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views: Vec<String> = Vec::new();
+    let mut accessors: Vec<String> = Vec::new();
+    let mut attributes: Vec<String> = Vec::new();
+
+    let positions: Vec<[f32; 3]> = mesh.positions.iter().map(|&p| y_up(p)).collect();
+    let normals: Vec<[f32; 3]> = mesh.normals.iter().map(|&n| y_up(n)).collect();
+    let (min, max) = bounds(&positions);
+
+    let mut add_view = |bytes: Vec<u8>, target: u32, buffer: &mut Vec<u8>| -> usize {
+        let offset = buffer.len();
+        views.push(format!("{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}", offset, bytes.len(), target));
+        buffer.extend(bytes);
+        // Every view starts 4 byte aligned
+        while !buffer.len().is_multiple_of(4) {
+            buffer.push(0);
+        }
+        views.len() - 1
+    };
+    let view = add_view(f32_bytes(positions.iter().flatten()), ARRAY_BUFFER, &mut buffer);
+    accessors.push(format!(
+        "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"VEC3\",\"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+        view, FLOAT, positions.len(), min[0], min[1], min[2], max[0], max[1], max[2]
+    ));
+    attributes.push(format!("\"POSITION\":{}", accessors.len() - 1));
+
+    if !normals.is_empty() {
+        let view = add_view(f32_bytes(normals.iter().flatten()), ARRAY_BUFFER, &mut buffer);
+        accessors.push(format!("{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"VEC3\"}}", view, FLOAT, normals.len()));
+        attributes.push(format!("\"NORMAL\":{}", accessors.len() - 1));
+    }
+
+    if !mesh.uvs.is_empty() {
+        let view = add_view(f32_bytes(mesh.uvs.iter().flatten()), ARRAY_BUFFER, &mut buffer);
+        accessors.push(format!("{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"VEC2\"}}", view, FLOAT, mesh.uvs.len()));
+        attributes.push(format!("\"TEXCOORD_0\":{}", accessors.len() - 1));
+    }
+
+    let view = add_view(mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect(), ELEMENT_ARRAY_BUFFER, &mut buffer);
+    accessors.push(format!("{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"SCALAR\"}}", view, UNSIGNED_INT, mesh.indices.len()));
+    let indices_accessor = accessors.len() - 1;
+
+    let uri = match uri {
+        Some(uri) => format!(",\"uri\":\"{}\"", uri),
+        None => String::new()
+    };
+
+    let json = format!(
+        "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"Terrain Generator\"}},\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0,\"name\":\"terrain\"}}],\
+\"meshes\":[{{\"primitives\":[{{\"attributes\":{{{}}},\"indices\":{},\"mode\":4}}]}}],\"buffers\":[{{\"byteLength\":{}{}}}],\"bufferViews\":[{}],\"accessors\":[{}]}}",
+        attributes.join(","),
+        indices_accessor,
+        buffer.len(),
+        uri,
+        views.join(","),
+        accessors.join(",")
+    );
+
+    (json, buffer)
+}
+
+fn f32_bytes<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn write_gltf(writer: &mut impl Write, mesh: &ExportMesh) -> io::Result<()> {
+    // The JSON only depends on the buffer length, so build the buffer first and then the JSON with its data URI
+    let (_, buffer) = gltf_parts(mesh, None);
+    let uri = format!("data:application/octet-stream;base64,{}", base64(&buffer));
+    let (json, _) = gltf_parts(mesh, Some(&uri));
+
+    writer.write_all(json.as_bytes())
+}
+
+fn write_glb(writer: &mut impl Write, mesh: &ExportMesh) -> io::Result<()> {
+    let (json, mut buffer) = gltf_parts(mesh, None);
+
+    // Chunks are 4 byte aligned, JSON is padded with spaces and the binary chunk with zeros
+    let mut json = json.into_bytes();
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+
+    let total_length = 12 + 8 + json.len() + 8 + buffer.len();
+    writer.write_all(b"glTF")?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(total_length as u32).to_le_bytes())?;
+
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(b"JSON")?;
+    writer.write_all(&json)?;
+
+    writer.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    writer.write_all(b"BIN\0")?;
+    writer.write_all(&buffer)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for group in bytes.chunks(3) {
+        let value = (group[0] as u32) << 16 | (*group.get(1).unwrap_or(&0) as u32) << 8 | *group.get(2).unwrap_or(&0) as u32;
+        for i in 0 .. 4 {
+            if i <= group.len() {
+                encoded.push(ALPHABET[(value >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
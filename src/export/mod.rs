@@ -0,0 +1,3 @@
+pub mod heightmap;
+pub mod mesh;
+pub mod rivers;
@@ -0,0 +1,38 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::export::heightmap::json_number;
+use crate::hydrology::RiverNetwork;
+
+// River Exporter
+// Writes the river polylines as GeoJSON, one LineString feature per river with [x, y, water height] positions
+// in world coordinates, readable by GIS tools and easy to parse for placing props along the rivers
+// The water mask is a height grid and is written with export_heightmap
+/* Example:
+let network = Rivers::default().generate(&mut grid);
+export_rivers(&network, "rivers.geojson")?;
+*/
+
+pub fn export_rivers(network: &RiverNetwork, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "{{\n  \"type\": \"FeatureCollection\",\n  \"features\": [")?;
+
+    for (i, polyline) in network.polylines.iter().enumerate() {
+        let coordinates: Vec<String> = polyline.iter()
+            .map(|p| format!("[{}, {}, {}]", json_number(p[0]), json_number(p[1]), json_number(p[2])))
+            .collect();
+        let separator = if i + 1 < network.polylines.len() { "," } else { "" };
+
+        writeln!(
+            out,
+            "    {{ \"type\": \"Feature\", \"properties\": {{ \"river\": {} }}, \"geometry\": {{ \"type\": \"LineString\", \"coordinates\": [{}] }} }}{}",
+            i,
+            coordinates.join(", "),
+            separator
+        )?;
+    }
+
+    writeln!(out, "  ]\n}}")?;
+    out.flush()
+}
@@ -0,0 +1,195 @@
+use crate::height_grid::{GridPass, HeightGrid};
+use crate::height_source::{HeightSource, SharedHeightSource};
+
+// Height Filters
+// Post-processing of sampled terrain: smoothing, sharpening, remapping, clamping, plateaus, island falloff
+// and blending with a second field, chained with the erosion passes in a pipeline
+// Distances (sigma, radius) are in grid cells or fractions of the grid, heights in the units of the grid
+/* Example:
+let pipeline = Pipeline::new()
+    .then(Filter::Blur { sigma: 1.5 })
+    .then(HydraulicErosion::default())
+    .then(Filter::Island { radius: 0.6, falloff: 0.3, floor: -0.5 })
+    .then(Filter::Clamp { min: -0.5, max: 0.8 });
+
+let grid = pipeline.run(&perlin_map, 0.0, 0.0, 0.02, 512, 512);
+let (vertices, indices, _) = generate_mesh(0.02, 512, 512, 0.0, 0.0, &Heightmap::from_grid(&grid));
+*/
+
+#[derive(Clone)]
+pub enum Filter {
+    // Gaussian smoothing, sigma in cells
+    Blur { sigma: f32 },
+    // Unsharp mask: adds amount times the difference to the blurred grid, bringing out ridges and small details
+    Sharpen { sigma: f32, amount: f32 },
+    // Remaps heights through [input, output] points sorted by input, linear between points and flat past the ends
+    Curve(Vec<[f32; 2]>),
+    Clamp { min: f32, max: f32 },
+    // Presses heights above the level towards it, strength 1.0 flattens them completely
+    Plateau { height: f32, strength: f32 },
+    // Fades the terrain to the floor height towards the edges of the grid
+    // Full height within radius of the centre, floor at radius + falloff, both as fractions of half the grid size
+    Island { radius: f32, falloff: f32, floor: f32 },
+    // Blends towards a second field where the mask is high, mask heights from mask_range[0] to mask_range[1] give 0 to 100% of other
+    // Both are sampled at the placement of the grid, a range with mask_range[0] >= mask_range[1] switches hard at mask_range[1]
+    Mix { other: SharedHeightSource, mask: SharedHeightSource, mask_range: [f32; 2] }
+}
+
+impl GridPass for Filter {
+    fn apply(&self, grid: &mut HeightGrid) {
+        match self {
+            Filter::Blur { sigma } => {
+                let blurred = blur(grid, *sigma);
+                grid.data_mut().copy_from_slice(&blurred);
+            },
+            Filter::Sharpen { sigma, amount } => {
+                let blurred = blur(grid, *sigma);
+                for (h, b) in grid.data_mut().iter_mut().zip(blurred) {
+                    *h += amount * (*h - b);
+                }
+            },
+            Filter::Curve(points) => {
+                grid.data_mut().iter_mut().for_each(|h| *h = curve(points, *h));
+            },
+            Filter::Clamp { min, max } => {
+                grid.data_mut().iter_mut().for_each(|h| *h = h.clamp(*min, *max));
+            },
+            Filter::Plateau { height, strength } => {
+                for h in grid.data_mut().iter_mut().filter(|h| **h > *height) {
+                    *h = *height + (*h - height) * (1.0 - strength.clamp(0.0, 1.0));
+                }
+            },
+            Filter::Island { radius, falloff, floor } => {
+                let (width, height) = (grid.width(), grid.height());
+                let (half_w, half_h) = ((width - 1).max(1) as f32 / 2.0, (height - 1).max(1) as f32 / 2.0);
+
+                for y in 0 .. height {
+                    for x in 0 .. width {
+                        // 0.0 in the centre, 1.0 in the middle of the edges, also for grids that are not square
+                        let (dx, dy) = ((x as f32 - half_w) / half_w, (y as f32 - half_h) / half_h);
+                        let distance = (dx * dx + dy * dy).sqrt();
+                        let t = ((distance - radius) / falloff.max(f32::EPSILON)).clamp(0.0, 1.0);
+                        let keep = 1.0 - t * t * (3.0 - 2.0 * t);
+
+                        let h = grid.get(x, y);
+                        grid.set(x, y, floor + (h - floor) * keep);
+                    }
+                }
+            },
+            Filter::Mix { other, mask, mask_range } => {
+                let other = sample_like(grid, other.as_ref());
+                let mask = sample_like(grid, mask.as_ref());
+                let [low, high] = *mask_range;
+
+                for ((h, o), m) in grid.data_mut().iter_mut().zip(other.data()).zip(mask.data()) {
+                    // A range without width is a hard step, dividing by it would give NaN on the step
+                    let weight = if high <= low {
+                        if *m >= high { 1.0 } else { 0.0 }
+                    } else {
+                        ((m - low) / (high - low)).clamp(0.0, 1.0)
+                    };
+                    *h += (o - *h) * weight;
+                }
+            }
+        }
+    }
+}
+
+// Passes applied one after another, filters and erosion mixed freely
+#[derive(Default)]
+pub struct Pipeline {
+    passes: Vec<Box<dyn GridPass + Send + Sync>>
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline { passes: Vec::new() }
+    }
+
+    pub fn then(mut self, pass: impl GridPass + Send + Sync + 'static) -> Self {
+        self.push(pass);
+        self
+    }
+
+    pub fn push(&mut self, pass: impl GridPass + Send + Sync + 'static) {
+        self.passes.push(Box::new(pass));
+    }
+
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    // Samples a region of the source (see HeightGrid::sample) and runs the passes over it
+    pub fn run(&self, source: &(impl HeightSource + ?Sized), origin_x: f32, origin_y: f32, step: f32, width: usize, height: usize) -> HeightGrid {
+        let mut grid = HeightGrid::sample(source, origin_x, origin_y, step, width, height);
+        self.apply(&mut grid);
+        grid
+    }
+}
+
+impl GridPass for Pipeline {
+    fn apply(&self, grid: &mut HeightGrid) {
+        for pass in self.passes.iter() {
+            pass.apply(grid);
+        }
+    }
+}
+
+// Separable Gaussian blur, the edges are extended so they keep their height
+fn blur(grid: &HeightGrid, sigma: f32) -> Vec<f32> {
+    let (width, height) = (grid.width(), grid.height());
+    if sigma <= 0.0 || width == 0 || height == 0 {
+        return grid.data().to_vec();
+    }
+
+    let radius = (sigma * 3.0).ceil() as i64;
+    let kernel: Vec<f32> = (-radius ..= radius).map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let total: f32 = kernel.iter().sum();
+    let kernel: Vec<f32> = kernel.iter().map(|k| k / total).collect();
+
+    let pass = |source: &[f32], along_x: bool| {
+        let mut out = vec![0.0; source.len()];
+        for y in 0 .. height {
+            for x in 0 .. width {
+                out[y * width + x] = kernel.iter().enumerate().map(|(k, weight)| {
+                    let offset = k as i64 - radius;
+                    let i = if along_x {
+                        y * width + (x as i64 + offset).clamp(0, width as i64 - 1) as usize
+                    } else {
+                        (y as i64 + offset).clamp(0, height as i64 - 1) as usize * width + x
+                    };
+                    weight * source[i]
+                }).sum();
+            }
+        }
+        out
+    };
+
+    pass(&pass(grid.data(), true), false)
+}
+
+fn curve(points: &[[f32; 2]], h: f32) -> f32 {
+    match points {
+        [] => h,
+        [first, ..] if h <= first[0] => first[1],
+        [.., last] if h >= last[0] => last[1],
+        // Only a NaN height gets here without a point above it, it passes through unchanged
+        _ => match points.iter().position(|p| p[0] > h) {
+            Some(i) => {
+                let ([x0, y0], [x1, y1]) = (points[i - 1], points[i]);
+                y0 + (y1 - y0) * (h - x0) / (x1 - x0)
+            },
+            None => h
+        }
+    }
+}
+
+// A second field sampled at the same positions as the grid
+fn sample_like(grid: &HeightGrid, source: &(dyn HeightSource + Send + Sync)) -> HeightGrid {
+    let (origin_x, origin_y) = grid.origin();
+    HeightGrid::sample(source, origin_x, origin_y, grid.step(), grid.width(), grid.height())
+}
@@ -0,0 +1,101 @@
+use crate::height_source::HeightSource;
+use crate::perlin_noise::PerlinMap;
+
+// Fractal Noise
+// Sums several octaves of a Perlin map: every octave doubles the frequency (lacunarity) and halves the amplitude (persistence),
+// adding smaller and smaller details on top of the large shapes
+// The sum is divided by the total amplitude, so any number of octaves stays in the range of a single one
+// and 1 octave gives exactly the heights of the map itself
+/* Example:
+let mut perlin_map = PerlinMap::new();
+perlin_map.set_hashed(42);
+
+let terrain = FractalNoise::new(perlin_map, 5);
+let z = terrain.height(1.3, 2.7);
+*/
+
+#[derive(Debug)]
+pub struct FractalNoise {
+    base: PerlinMap,
+    octaves: u32,
+    lacunarity: f32,
+    persistence: f32
+}
+
+impl FractalNoise {
+    pub fn new(base: PerlinMap, octaves: u32) -> Self {
+        FractalNoise {
+            base,
+            octaves: octaves.max(1),
+            lacunarity: 2.0,
+            persistence: 0.5
+        }
+    }
+
+    pub fn set_octaves(&mut self, octaves: u32) {
+        self.octaves = octaves.max(1);
+    }
+
+    pub fn get_octaves(&self) -> u32 {
+        self.octaves
+    }
+
+    // Frequency multiplier between octaves
+    pub fn set_lacunarity(&mut self, lacunarity: f32) {
+        self.lacunarity = lacunarity;
+    }
+
+    // Amplitude multiplier between octaves
+    pub fn set_persistence(&mut self, persistence: f32) {
+        self.persistence = persistence;
+    }
+
+    pub fn get_base(&self) -> &PerlinMap {
+        &self.base
+    }
+
+    // Frequency, amplitude and offset of every octave
+    // Octaves are shifted against each other, otherwise all of them would be 0 at the origin lattice point
+    fn octave_params(&self) -> impl Iterator<Item = (f32, f32, f32)> + '_ {
+        (0 .. self.octaves).map(|i| (self.lacunarity.powi(i as i32), self.persistence.powi(i as i32), i as f32 * 19.37))
+    }
+
+    fn total_amplitude(&self) -> f32 {
+        self.octave_params().map(|(_, amplitude, _)| amplitude).sum()
+    }
+}
+
+impl HeightSource for FractalNoise {
+    fn height(&self, x: f32, y: f32) -> f32 {
+        let sum: f32 = self.octave_params().map(|(frequency, amplitude, offset)| amplitude * self.base.noise(x * frequency + offset, y * frequency + offset)).sum();
+        sum / self.total_amplitude()
+    }
+
+    // One noise_grid call per octave
+    fn fill_grid(&self, xs: &[f32], ys: &[f32], out: &mut [f32]) {
+        out.iter_mut().for_each(|h| *h = 0.0);
+        let mut octave = vec![0.0; out.len()];
+
+        for (frequency, amplitude, offset) in self.octave_params() {
+            let octave_xs: Vec<f32> = xs.iter().map(|x| x * frequency + offset).collect();
+            let octave_ys: Vec<f32> = ys.iter().map(|y| y * frequency + offset).collect();
+            self.base.noise_grid(&octave_xs, &octave_ys, &mut octave);
+
+            for (h, o) in out.iter_mut().zip(octave.iter()) {
+                *h += amplitude * o;
+            }
+        }
+
+        let total = self.total_amplitude();
+        out.iter_mut().for_each(|h| *h /= total);
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.base.get_reproducible_seed()
+    }
+
+    // Higher octaves of a bounded stored map reach past its edge and flatten out there, the bounds of the first octave are used
+    fn is_valid_coord(&self, scale: f32, plain_h: i32, plain_w: i32, x: f32, y: f32) -> bool {
+        self.base.is_valid_coord(scale, plain_h, plain_w, x, y)
+    }
+}
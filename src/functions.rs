@@ -1,15 +1,20 @@
-use crate::perlin_noise::PerlinMap;
+use crate::height_source::HeightSource;
+use crate::perlin_noise::grid_coords;
 
-pub fn generate_mesh(scale: f32, plain_h: i32, plain_w: i32, pos_x: f32, pos_y: f32, p_map: &PerlinMap) -> (Vec<f32>, Vec<i32>, i32) {
+pub fn generate_mesh(scale: f32, plain_h: i32, plain_w: i32, pos_x: f32, pos_y: f32, source: &(impl HeightSource + ?Sized)) -> (Vec<f32>, Vec<i32>, i32) {
     let mut vertices: Vec<f32> = Vec::new();
     let mut indices: Vec<i32> = Vec::new();
     let mut triangle_count: i32 = 0;
+
+    // Sample all heights in one batch
+    let mut heights = vec![0.0; (plain_h * plain_w) as usize];
+    source.fill_grid(&grid_coords(pos_x, scale, plain_w as usize), &grid_coords(pos_y, scale, plain_h as usize), &mut heights);
     
     // Populate map
     for i in 0 .. plain_h * plain_w {
         let x = (i % plain_w) as f32; // Column
         let y = (i / plain_w) as f32; // Row
-        let z = p_map.noise((x * scale) + pos_x, (y * scale) + pos_y);
+        let z = heights[i as usize];
         
         // Normalize to UV coordinates (0.0 to 1.0)
         let u = (x / (plain_w - 1) as f32) * 2.0 - 1.0; // X
@@ -21,10 +26,10 @@ pub fn generate_mesh(scale: f32, plain_h: i32, plain_w: i32, pos_x: f32, pos_y:
         
         // Generate indices (except for the last row and column)
         if x < (plain_w - 1) as f32 && y < (plain_h - 1) as f32 {
-            let top_left = i as i32;
-            let top_right = (i + 1) as i32;
-            let bottom_left = (i + plain_w) as i32;
-            let bottom_right = (i + plain_w + 1) as i32;
+            let top_left = i;
+            let top_right = i + 1;
+            let bottom_left = i + plain_w;
+            let bottom_right = i + plain_w + 1;
             
             // First triangle (Top Left, Bottom Left, Bottom Right)
             indices.push(top_left);
@@ -41,4 +46,81 @@ pub fn generate_mesh(scale: f32, plain_h: i32, plain_w: i32, pos_x: f32, pos_y:
     }
     
     (vertices, indices, triangle_count)
-}
\ No newline at end of file
+}
+
+// Mesh for one terrain chunk in world coordinates (the same units as the noise input), relative to the chunk origin
+// A chunk at LOD level L has chunk_size x chunk_size cells spaced scale * 2^L apart,
+// so the vertices of a coarse chunk line up with every other vertex of the finer chunk next to it
+// The border gets a skirt hanging skirt_depth below the surface to hide cracks between chunks of different levels
+pub fn generate_chunk_mesh(scale: f32, chunk_size: i32, level: u32, chunk_x: i32, chunk_y: i32, skirt_depth: f32, source: &(impl HeightSource + ?Sized)) -> (Vec<f32>, Vec<i32>, i32) {
+    let mut vertices: Vec<f32> = Vec::new();
+    let side = chunk_size + 1;
+    let step = 1i32 << level;
+    let spacing = scale * step as f32;
+
+    // Global level 0 grid position keeps shared edges bit for bit identical between chunks
+    let xs: Vec<f32> = (0 .. side).map(|i| ((chunk_x * chunk_size + i) * step) as f32 * scale).collect();
+    let ys: Vec<f32> = (0 .. side).map(|i| ((chunk_y * chunk_size + i) * step) as f32 * scale).collect();
+    let mut heights = vec![0.0; (side * side) as usize];
+    source.fill_grid(&xs, &ys, &mut heights);
+
+    for (i, z) in heights.into_iter().enumerate() {
+        let i = i as i32;
+        vertices.push((i % side) as f32 * spacing);
+        vertices.push((i / side) as f32 * spacing);
+        vertices.push(z);
+    }
+
+    let (mut indices, mut triangle_count) = generate_grid_indices(side, side);
+
+    if skirt_depth > 0.0 {
+        // Border vertices in order around the chunk
+        let mut border: Vec<i32> = Vec::new();
+        border.extend(0 .. side - 1); // Top row
+        border.extend((0 .. side - 1).map(|y| y * side + side - 1)); // Right column
+        border.extend((1 .. side).rev().map(|x| (side - 1) * side + x)); // Bottom row
+        border.extend((1 .. side).rev().map(|y| y * side)); // Left column
+
+        // Copy of every border vertex pushed down by the skirt depth
+        let skirt_start = side * side;
+        for &b in border.iter() {
+            let v = b as usize * 3;
+            vertices.extend_from_slice(&[vertices[v], vertices[v + 1], vertices[v + 2] - skirt_depth]);
+        }
+
+        for i in 0 .. border.len() {
+            let next = (i + 1) % border.len();
+            let (top_a, top_b) = (border[i], border[next]);
+            let (bottom_a, bottom_b) = (skirt_start + i as i32, skirt_start + next as i32);
+
+            indices.extend_from_slice(&[top_a, bottom_a, bottom_b]);
+            indices.extend_from_slice(&[top_a, bottom_b, top_b]);
+            triangle_count += 2;
+        }
+    }
+
+    (vertices, indices, triangle_count)
+}
+
+// Two triangles per grid cell for a row-major grid of rows x cols vertices
+pub fn generate_grid_indices(rows: i32, cols: i32) -> (Vec<i32>, i32) {
+    let mut indices: Vec<i32> = Vec::new();
+    let mut triangle_count: i32 = 0;
+
+    for y in 0 .. rows - 1 {
+        for x in 0 .. cols - 1 {
+            let top_left = y * cols + x;
+            let top_right = top_left + 1;
+            let bottom_left = top_left + cols;
+            let bottom_right = bottom_left + 1;
+
+            // First triangle (Top Left, Bottom Left, Bottom Right)
+            indices.extend_from_slice(&[top_left, bottom_left, bottom_right]);
+            // Second triangle (Top Left, Bottom Right, Top Right)
+            indices.extend_from_slice(&[top_left, bottom_right, top_right]);
+            triangle_count += 2;
+        }
+    }
+
+    (indices, triangle_count)
+}
@@ -0,0 +1,206 @@
+use glfw::{GamepadButton, Key, MouseButton};
+use std::collections::HashMap;
+use std::fs;
+
+// Input Bindings
+// Maps named actions to keys, mouse buttons and gamepad buttons
+/* Example:
+let bindings = InputBindings::load("resources/controls.cfg");
+window.set_bindings(bindings);
+
+if window.is_action_pressed(InputAction::MoveForward) { ... }
+if window.is_action_just_pressed(InputAction::ToggleMode) { ... }
+*/
+
+/* Config file format (one action per line, bindings separated by commas):
+# Comments start with #
+move_forward = W, Up, GamepadDpadUp
+toggle_mode = F1, GamepadStart
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    RotateClockwise,
+    RotateAntiClockwise,
+    ToggleMode
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton)
+}
+
+const ACTION_NAMES: &[(&str, InputAction)] = &[
+    ("move_forward", InputAction::MoveForward),
+    ("move_backward", InputAction::MoveBackward),
+    ("move_left", InputAction::MoveLeft),
+    ("move_right", InputAction::MoveRight),
+    ("rotate_clockwise", InputAction::RotateClockwise),
+    ("rotate_anti_clockwise", InputAction::RotateAntiClockwise),
+    ("toggle_mode", InputAction::ToggleMode)
+];
+
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("Space", Key::Space), ("Apostrophe", Key::Apostrophe), ("Comma", Key::Comma), ("Minus", Key::Minus),
+    ("Period", Key::Period), ("Slash", Key::Slash), ("Semicolon", Key::Semicolon), ("Equal", Key::Equal),
+    ("0", Key::Num0), ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3), ("4", Key::Num4),
+    ("5", Key::Num5), ("6", Key::Num6), ("7", Key::Num7), ("8", Key::Num8), ("9", Key::Num9),
+    ("A", Key::A), ("B", Key::B), ("C", Key::C), ("D", Key::D), ("E", Key::E), ("F", Key::F),
+    ("G", Key::G), ("H", Key::H), ("I", Key::I), ("J", Key::J), ("K", Key::K), ("L", Key::L),
+    ("M", Key::M), ("N", Key::N), ("O", Key::O), ("P", Key::P), ("Q", Key::Q), ("R", Key::R),
+    ("S", Key::S), ("T", Key::T), ("U", Key::U), ("V", Key::V), ("W", Key::W), ("X", Key::X),
+    ("Y", Key::Y), ("Z", Key::Z),
+    ("LeftBracket", Key::LeftBracket), ("Backslash", Key::Backslash), ("RightBracket", Key::RightBracket),
+    ("GraveAccent", Key::GraveAccent), ("Enter", Key::Enter), ("Tab", Key::Tab), ("Backspace", Key::Backspace),
+    ("Insert", Key::Insert), ("Delete", Key::Delete), ("Right", Key::Right), ("Left", Key::Left),
+    ("Down", Key::Down), ("Up", Key::Up), ("PageUp", Key::PageUp), ("PageDown", Key::PageDown),
+    ("Home", Key::Home), ("End", Key::End),
+    ("F1", Key::F1), ("F2", Key::F2), ("F3", Key::F3), ("F4", Key::F4), ("F5", Key::F5), ("F6", Key::F6),
+    ("F7", Key::F7), ("F8", Key::F8), ("F9", Key::F9), ("F10", Key::F10), ("F11", Key::F11), ("F12", Key::F12),
+    ("Kp0", Key::Kp0), ("Kp1", Key::Kp1), ("Kp2", Key::Kp2), ("Kp3", Key::Kp3), ("Kp4", Key::Kp4),
+    ("Kp5", Key::Kp5), ("Kp6", Key::Kp6), ("Kp7", Key::Kp7), ("Kp8", Key::Kp8), ("Kp9", Key::Kp9),
+    ("LeftShift", Key::LeftShift), ("LeftControl", Key::LeftControl), ("LeftAlt", Key::LeftAlt),
+    ("RightShift", Key::RightShift), ("RightControl", Key::RightControl), ("RightAlt", Key::RightAlt)
+];
+
+const MOUSE_NAMES: &[(&str, MouseButton)] = &[
+    ("MouseLeft", MouseButton::Button1),
+    ("MouseRight", MouseButton::Button2),
+    ("MouseMiddle", MouseButton::Button3),
+    ("Mouse4", MouseButton::Button4),
+    ("Mouse5", MouseButton::Button5)
+];
+
+const GAMEPAD_NAMES: &[(&str, GamepadButton)] = &[
+    ("GamepadA", GamepadButton::ButtonA),
+    ("GamepadB", GamepadButton::ButtonB),
+    ("GamepadX", GamepadButton::ButtonX),
+    ("GamepadY", GamepadButton::ButtonY),
+    ("GamepadLeftBumper", GamepadButton::ButtonLeftBumper),
+    ("GamepadRightBumper", GamepadButton::ButtonRightBumper),
+    ("GamepadBack", GamepadButton::ButtonBack),
+    ("GamepadStart", GamepadButton::ButtonStart),
+    ("GamepadGuide", GamepadButton::ButtonGuide),
+    ("GamepadLeftThumb", GamepadButton::ButtonLeftThumb),
+    ("GamepadRightThumb", GamepadButton::ButtonRightThumb),
+    ("GamepadDpadUp", GamepadButton::ButtonDpadUp),
+    ("GamepadDpadRight", GamepadButton::ButtonDpadRight),
+    ("GamepadDpadDown", GamepadButton::ButtonDpadDown),
+    ("GamepadDpadLeft", GamepadButton::ButtonDpadLeft)
+];
+
+impl InputAction {
+    pub fn from_name(name: &str) -> Option<InputAction> {
+        ACTION_NAMES.iter().find(|(n, _)| *n == name).map(|(_, a)| *a)
+    }
+}
+
+impl Binding {
+    // Names are case insensitive ("w", "W", "mouseleft" and "MouseLeft" are all valid)
+    pub fn from_name(name: &str) -> Option<Binding> {
+        if let Some((_, key)) = KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            return Some(Binding::Key(*key));
+        }
+        if let Some((_, button)) = MOUSE_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            return Some(Binding::Mouse(*button));
+        }
+        if let Some((_, button)) = GAMEPAD_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            return Some(Binding::Gamepad(*button));
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InputBindings {
+    bindings: HashMap<InputAction, Vec<Binding>>
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings::new()
+    }
+}
+
+impl InputBindings {
+    // Defaults match the controls table in README.md
+    pub fn new() -> Self {
+        let mut bindings = InputBindings { bindings: HashMap::new() };
+
+        bindings.bind(InputAction::MoveForward, vec![Binding::Key(Key::W), Binding::Gamepad(GamepadButton::ButtonDpadUp)]);
+        bindings.bind(InputAction::MoveBackward, vec![Binding::Key(Key::S), Binding::Gamepad(GamepadButton::ButtonDpadDown)]);
+        bindings.bind(InputAction::MoveLeft, vec![Binding::Key(Key::A), Binding::Gamepad(GamepadButton::ButtonDpadLeft)]);
+        bindings.bind(InputAction::MoveRight, vec![Binding::Key(Key::D), Binding::Gamepad(GamepadButton::ButtonDpadRight)]);
+        bindings.bind(InputAction::RotateClockwise, vec![Binding::Key(Key::Q), Binding::Gamepad(GamepadButton::ButtonLeftBumper)]);
+        bindings.bind(InputAction::RotateAntiClockwise, vec![Binding::Key(Key::E), Binding::Gamepad(GamepadButton::ButtonRightBumper)]);
+        bindings.bind(InputAction::ToggleMode, vec![Binding::Key(Key::F1), Binding::Gamepad(GamepadButton::ButtonStart)]);
+
+        bindings
+    }
+
+    // Load bindings from a config file, actions missing from the file keep their default bindings
+    // A missing or invalid file falls back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(source) => match Self::parse(&source) {
+                Ok(bindings) => bindings,
+                Err(e) => {
+                    println!("Invalid controls file {}: {}, using default controls", path, e);
+                    Self::new()
+                }
+            },
+            Err(_) => {
+                println!("Controls file {} not found, using default controls", path);
+                Self::new()
+            }
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut bindings = Self::new();
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, values) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `action = binding, ...`", line_number + 1))?;
+
+            let action = InputAction::from_name(name.trim())
+                .ok_or_else(|| format!("line {}: unknown action `{}`", line_number + 1, name.trim()))?;
+
+            bindings.bind(action, Self::parse_binding_list(values).map_err(|e| format!("line {}: {}", line_number + 1, e))?);
+        }
+
+        Ok(bindings)
+    }
+
+    // Parse a comma separated list of binding names, an empty list unbinds the action
+    pub fn parse_binding_list(values: &str) -> Result<Vec<Binding>, String> {
+        let mut list = Vec::new();
+
+        for value in values.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            list.push(Binding::from_name(value).ok_or_else(|| format!("unknown binding `{}`", value))?);
+        }
+
+        Ok(list)
+    }
+
+    pub fn bind(&mut self, action: InputAction, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+
+    pub fn get(&self, action: InputAction) -> &[Binding] {
+        self.bindings.get(&action).map(|b| b.as_slice()).unwrap_or(&[])
+    }
+}
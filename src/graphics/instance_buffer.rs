@@ -0,0 +1,51 @@
+use super::vbo::BufferObject;
+use super::v_attribute::VertexAttribute;
+
+// Instance Buffer
+// Per-instance data for instanced draws: interleaved floats, the layout gives the float count of every attribute
+// The attributes advance once per instance and are placed after the vertex attributes of the mesh drawn with it
+/* Example:
+// Position and scale (vec4), then rotation (float) of every instance
+let instances = InstanceBuffer::new(&[0.0, 0.0, 0.1, 0.05, 1.2, 0.5, 0.3, 0.1, 0.04, 0.0], &[4, 1]);
+tree.draw_instances(&instances);
+*/
+
+pub struct InstanceBuffer {
+    vbo: BufferObject,
+    layout: Vec<i32>,
+    count: usize
+}
+
+impl InstanceBuffer {
+    // data must hold at least one instance
+    pub fn new(data: &[f32], layout: &[i32]) -> InstanceBuffer {
+        let vbo = BufferObject::new(gl::ARRAY_BUFFER, gl::DYNAMIC_DRAW);
+        let mut buffer = InstanceBuffer { vbo, layout: layout.to_vec(), count: 0 };
+        buffer.update(data);
+        buffer
+    }
+
+    // Replace the instances, the layout stays the same
+    pub fn update(&mut self, data: &[f32]) {
+        self.vbo.bind();
+        self.vbo.store_f32_data(data);
+        self.vbo.unbind();
+
+        self.count = data.len() / self.layout.iter().sum::<i32>().max(1) as usize;
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // Points the instance attributes of the bound vertex array at this buffer, from first_location on
+    pub fn bind_attributes(&self, first_location: u32) {
+        self.vbo.bind();
+        VertexAttribute::interleaved(&self.layout, first_location, 1);
+        self.vbo.unbind();
+    }
+}
@@ -0,0 +1,91 @@
+use std::ptr;
+
+use super::vao::ArrayObject;
+use super::vbo::BufferObject;
+use super::v_attribute::VertexAttribute;
+use super::instance_buffer::InstanceBuffer;
+
+// Mesh
+// Owns the VAO, VBO and IBO of an indexed triangle mesh with xyz positions at attribute location 0
+// Meshes with more per-vertex data interleave it and give the float count of every attribute as the layout
+// The GPU buffers are freed when the mesh is dropped
+/* Example:
+let mesh = Mesh::new(&vertices, &indices);
+shader.bind();
+mesh.draw();
+
+// xyz at location 0 and one float at location 1
+let water = Mesh::with_layout(&water_vertices, &water_indices, &[3, 1]);
+
+// 64 copies, the shader places each one by gl_InstanceID
+tree.draw_instanced(64);
+
+// One copy per instance in the buffer, its attributes follow the vertex attributes (location 1 and up here)
+tree.draw_instances(&instances);
+*/
+
+pub struct Mesh {
+    vao: ArrayObject,
+    vbo: BufferObject,
+    ibo: BufferObject,
+    index_count: i32,
+    attribute_count: u32
+}
+
+impl Mesh {
+    pub fn new(vertices: &[f32], indices: &[i32]) -> Mesh {
+        Mesh::with_layout(vertices, indices, &[3])
+    }
+
+    pub fn with_layout(vertices: &[f32], indices: &[i32], layout: &[i32]) -> Mesh {
+        let vao = ArrayObject::new();
+        vao.bind();
+
+        let vbo = BufferObject::new(gl::ARRAY_BUFFER, gl::STATIC_DRAW);
+        vbo.bind();
+        vbo.store_f32_data(vertices);
+
+        let ibo = BufferObject::new(gl::ELEMENT_ARRAY_BUFFER, gl::STATIC_DRAW);
+        ibo.bind();
+        ibo.store_i32_data(indices);
+
+        VertexAttribute::interleaved(layout, 0, 0);
+
+        vao.unbind();
+
+        Mesh { vao, vbo, ibo, index_count: indices.len() as i32, attribute_count: layout.len() as u32 }
+    }
+
+    // Replace the mesh data, the vertex layout stays the same
+    pub fn update(&mut self, vertices: &[f32], indices: &[i32]) {
+        self.vao.bind();
+        self.vbo.bind();
+        self.vbo.store_f32_data(vertices);
+        self.ibo.bind();
+        self.ibo.store_i32_data(indices);
+        self.vao.unbind();
+
+        self.index_count = indices.len() as i32;
+    }
+
+    pub fn draw(&self) {
+        self.vao.bind();
+        unsafe {
+            gl::DrawElements(gl::TRIANGLES, self.index_count, gl::UNSIGNED_INT, ptr::null());
+        }
+    }
+
+    pub fn draw_instanced(&self, instance_count: i32) {
+        self.vao.bind();
+        unsafe {
+            gl::DrawElementsInstanced(gl::TRIANGLES, self.index_count, gl::UNSIGNED_INT, ptr::null(), instance_count);
+        }
+    }
+
+    // The instance attributes are pointed at the buffer on every call, so one mesh can be drawn with many buffers
+    pub fn draw_instances(&self, instances: &InstanceBuffer) {
+        self.vao.bind();
+        instances.bind_attributes(self.attribute_count);
+        self.draw_instanced(instances.len() as i32);
+    }
+}
@@ -4,3 +4,6 @@ pub mod vbo;
 pub mod v_attribute;
 pub mod shader_reader;
 pub mod object_loader;
+pub mod input;
+pub mod mesh;
+pub mod instance_buffer;
@@ -1,8 +1,11 @@
 use gltf;
 
+use crate::culling::Aabb;
+
 pub struct ObjectLoader {
     vertices: Vec<f32>,
-    indices: Vec<u32>
+    indices: Vec<u32>,
+    bounds: Aabb
 }
 
 impl ObjectLoader {
@@ -92,9 +95,12 @@ impl ObjectLoader {
             }
         }
 
+        let bounds = Aabb::from_points(&vertices);
+
         ObjectLoader {
             vertices,
-            indices
+            indices,
+            bounds
         }
     }
 
@@ -106,6 +112,11 @@ impl ObjectLoader {
         self.indices.iter().map(|&i| i as i32).collect()
     }
 
+    // Box around the vertex positions, in model space
+    pub fn get_bounds(&self) -> Aabb {
+        self.bounds
+    }
+
     pub fn get_triangle_count(&self) -> i32 {
         self.indices.len() as i32
     }
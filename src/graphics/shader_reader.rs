@@ -14,6 +14,8 @@ let mut program = ShaderReader::new("/path/to/vertex_shader.glsl", "/path/to/fra
 program.bind();
 program.create_uniform("transform");
 // program.set_matrix4fv_uniform("transform", some_matrix);
+program.create_uniform("colors");
+// program.set_3fv_uniform("colors", &[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
 */
 
 pub struct ShaderReader {
@@ -21,7 +23,7 @@ pub struct ShaderReader {
     uniform_ids: HashMap<String, GLint>
 }
 
-#[allow(temporary_cstring_as_ptr)]
+#[allow(dangling_pointers_from_temporaries)]
 impl ShaderReader {
     pub fn new(vertex_shader_path: &str, fragment_shader_path: &str) -> ShaderReader {
         let mut vertex_shader_file = File::open(vertex_shader_path)
@@ -100,4 +102,37 @@ impl ShaderReader {
             )
         }
     }
+
+    pub fn set_1i_uniform(&self, uniform_name: &str, value: i32) {
+        unsafe {
+            gl::Uniform1i(self.uniform_ids[uniform_name], value)
+        }
+    }
+
+    pub fn set_1f_uniform(&self, uniform_name: &str, value: f32) {
+        unsafe {
+            gl::Uniform1f(self.uniform_ids[uniform_name], value)
+        }
+    }
+
+    // Uniform float array, the name is the array itself ("heights" for uniform float heights[8])
+    pub fn set_1fv_uniform(&self, uniform_name: &str, values: &[f32]) {
+        unsafe {
+            gl::Uniform1fv(self.uniform_ids[uniform_name], values.len() as GLsizei, values.as_ptr())
+        }
+    }
+
+    // Uniform vec3 array
+    pub fn set_3fv_uniform(&self, uniform_name: &str, values: &[[f32; 3]]) {
+        unsafe {
+            gl::Uniform3fv(self.uniform_ids[uniform_name], values.len() as GLsizei, values.as_ptr() as *const GLfloat)
+        }
+    }
+
+    // Uniform vec4 array
+    pub fn set_4fv_uniform(&self, uniform_name: &str, values: &[[f32; 4]]) {
+        unsafe {
+            gl::Uniform4fv(self.uniform_ids[uniform_name], values.len() as GLsizei, values.as_ptr() as *const GLfloat)
+        }
+    }
 }
\ No newline at end of file
@@ -3,9 +3,13 @@ use gl::types::*;
 
 // Vertex Attribute
 // Discribes vertex data
+// With a divisor the attribute advances once per instance (or every divisor instances) instead of once per vertex
 /* Example:
 let position_attribute = VertexAttribute::new(0, 3, gl::FLOAT, gl::FALSE, 3 * mem::size_of::<GLfloat>() as GLsizei, ptr::null());
 position_attribute.enable();
+
+// Interleaved floats of the bound buffer: vec3 at location 0, float at location 1
+VertexAttribute::interleaved(&[3, 1], 0, 0);
 */
 
 pub struct VertexAttribute {
@@ -13,6 +17,8 @@ pub struct VertexAttribute {
 }
 
 impl VertexAttribute {
+    // With a buffer bound, pointer is a byte offset into it and is never dereferenced as a pointer
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn new(
         index: u32,
         size: i32,
@@ -28,6 +34,29 @@ impl VertexAttribute {
         VertexAttribute {index}
     }
 
+    // Enables float attributes interleaved in the bound buffer, one per layout entry with its float count,
+    // at consecutive locations from first_location
+    pub fn interleaved(layout: &[i32], first_location: u32, divisor: u32) -> Vec<VertexAttribute> {
+        let float_size = std::mem::size_of::<GLfloat>();
+        let stride = layout.iter().sum::<i32>() * float_size as GLsizei;
+        let mut offset = 0;
+
+        layout.iter().enumerate().map(|(i, &size)| {
+            let attribute = VertexAttribute::new(first_location + i as u32, size, gl::FLOAT, gl::FALSE, stride, (offset * float_size) as *const GLvoid);
+            attribute.enable();
+            attribute.set_divisor(divisor);
+            offset += size as usize;
+            attribute
+        }).collect()
+    }
+
+    // 0 advances per vertex, n advances every n instances
+    pub fn set_divisor(&self, divisor: u32) {
+        unsafe {
+            gl::VertexAttribDivisor(self.index, divisor);
+        }
+    }
+
     pub fn enable(&self) {
         unsafe {
             gl::EnableVertexAttribArray(self.index);
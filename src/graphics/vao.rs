@@ -10,6 +10,12 @@ pub struct ArrayObject {
     id: GLuint
 }
 
+impl Default for ArrayObject {
+    fn default() -> Self {
+        ArrayObject::new()
+    }
+}
+
 impl ArrayObject {
     pub fn new() -> ArrayObject {
         let mut id = 0;
@@ -31,4 +37,12 @@ impl ArrayObject {
             gl::BindVertexArray(0);
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for ArrayObject {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.id);
+        }
+    }
+}
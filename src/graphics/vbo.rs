@@ -37,7 +37,7 @@ impl BufferObject {
         unsafe {
             gl::BufferData(
                 self.r_type,
-                (data.len() * std::mem::size_of::<GLfloat>()) as GLsizeiptr,
+                std::mem::size_of_val(data) as GLsizeiptr,
                 &data[0] as *const f32 as *const _,
                 self.usage
             );
@@ -48,10 +48,18 @@ impl BufferObject {
         unsafe {
             gl::BufferData(
                 self.r_type,
-                (data.len() * std::mem::size_of::<GLfloat>()) as GLsizeiptr,
+                std::mem::size_of_val(data) as GLsizeiptr,
                 &data[0] as *const i32 as *const _,
                 self.usage
             );
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for BufferObject {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
@@ -1,7 +1,10 @@
-use glfw::{Action, Context, Key, WindowEvent};
+use glfw::{Action, Context, CursorMode, GamepadAxis, GamepadButton, JoystickId, Key, MouseButton, WindowEvent};
 use std::collections::HashSet;
 use std::sync::mpsc::Receiver;
 
+use super::input::{Binding, InputAction, InputBindings};
+use crate::timestep::FixedTimestep;
+
 // Application window
 /* Example:
 let mut window = Window::new(1280, 720, "Window");
@@ -14,13 +17,55 @@ while !window.close() {
     }
     window.update();
 }
+
+Mouse input:
+window.set_cursor_captured(true); // Hide the cursor and report relative motion
+let (dx, dy) = window.get_cursor_delta();
+let (_, scroll_y) = window.get_scroll_delta();
+if window.is_mouse_button_pressed(MouseButton::Button1) { ... }
+
+Actions (see input.rs for bindings):
+window.set_bindings(InputBindings::load("resources/controls.cfg"));
+if window.is_action_pressed(InputAction::MoveForward) { ... }
+if window.is_action_just_pressed(InputAction::ToggleMode) { ... }
+
+Gamepads (sampled every update, axes have deadzones applied):
+println!("{:?}", window.get_gamepads());
+let (forward, right) = window.get_movement_axes(); // WASD and left stick
+let turn = window.get_rotation_axis(); // QE and right stick
+
+Timing (measured between calls to update):
+let dt = window.get_delta_time();
+for _ in 0 .. window.get_fixed_steps() {
+    // update by window.get_fixed_step() seconds
+}
+let alpha = window.get_interpolation_alpha();
 */
 
 pub struct Window {
     glfw: glfw::Glfw,
     window_handle: glfw::Window,
     events: Receiver<(f64, WindowEvent)>,
-    keys_pressed: HashSet<Key>
+    keys_pressed: HashSet<Key>,
+    keys_just_pressed: HashSet<Key>,
+    mouse_buttons_pressed: HashSet<MouseButton>,
+    mouse_buttons_just_pressed: HashSet<MouseButton>,
+    gamepad_buttons_pressed: HashSet<GamepadButton>,
+    gamepad_buttons_just_pressed: HashSet<GamepadButton>,
+    gamepad_axes: [f32; 6],
+    gamepads: Vec<(JoystickId, String)>,
+    stick_deadzone: f32,
+    trigger_deadzone: f32,
+    bindings: InputBindings,
+    cursor_pos: (f64, f64),
+    cursor_delta: (f64, f64),
+    scroll_delta: (f64, f64),
+    cursor_captured: bool,
+    first_cursor_event: bool,
+    last_frame_time: f64,
+    delta_time: f32,
+    timestep: FixedTimestep,
+    fixed_steps: u32
 }
 
 impl Window {
@@ -34,8 +79,42 @@ impl Window {
 
         window.set_framebuffer_size_polling(true);
         window.set_key_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_mouse_button_polling(true);
+        window.set_scroll_polling(true);
+
+        let cursor_pos = window.get_cursor_pos();
+        let last_frame_time = glfw.get_time();
 
-        Window {glfw, window_handle: window, events, keys_pressed: HashSet::new()}
+        let mut window = Window {
+            glfw,
+            window_handle: window,
+            events,
+            keys_pressed: HashSet::new(),
+            keys_just_pressed: HashSet::new(),
+            mouse_buttons_pressed: HashSet::new(),
+            mouse_buttons_just_pressed: HashSet::new(),
+            gamepad_buttons_pressed: HashSet::new(),
+            gamepad_buttons_just_pressed: HashSet::new(),
+            gamepad_axes: [0.0; 6],
+            gamepads: Vec::new(),
+            stick_deadzone: 0.2,
+            trigger_deadzone: 0.1,
+            bindings: InputBindings::new(),
+            cursor_pos,
+            cursor_delta: (0.0, 0.0),
+            scroll_delta: (0.0, 0.0),
+            cursor_captured: false,
+            first_cursor_event: true,
+            last_frame_time,
+            delta_time: 0.0,
+            timestep: FixedTimestep::new(120.0),
+            fixed_steps: 0
+        };
+
+        // Enumerate gamepads that were connected before the window was created
+        window.poll_gamepads();
+        window
     }
 
     // Load gl functions.
@@ -52,9 +131,49 @@ impl Window {
         self.process_events();
         self.glfw.poll_events();
         self.window_handle.swap_buffers();
+        self.update_time();
+    }
+
+    fn update_time(&mut self) {
+        let now = self.glfw.get_time();
+        let frame_time = now - self.last_frame_time;
+        self.last_frame_time = now;
+
+        self.delta_time = frame_time as f32;
+        self.fixed_steps = self.timestep.advance(frame_time);
+    }
+
+    // Seconds between the last two updates
+    pub fn get_delta_time(&self) -> f32 {
+        self.delta_time
+    }
+
+    // Number of fixed steps to simulate this frame
+    pub fn get_fixed_steps(&self) -> u32 {
+        self.fixed_steps
+    }
+
+    // Length of a fixed step in seconds
+    pub fn get_fixed_step(&self) -> f32 {
+        self.timestep.get_step()
+    }
+
+    // Fraction of a fixed step left over after this frame's steps, for interpolating rendered state
+    pub fn get_interpolation_alpha(&self) -> f32 {
+        self.timestep.get_alpha()
+    }
+
+    pub fn set_fixed_update_rate(&mut self, updates_per_second: f64) {
+        self.timestep = FixedTimestep::new(updates_per_second);
     }
 
     fn process_events(&mut self) {
+        // Deltas only describe the events of the current frame
+        self.cursor_delta = (0.0, 0.0);
+        self.scroll_delta = (0.0, 0.0);
+        self.keys_just_pressed.clear();
+        self.mouse_buttons_just_pressed.clear();
+
         for (_, event) in glfw::flush_messages(&self.events) {
             match event {
                 glfw::WindowEvent::FramebufferSize(width, height) => {
@@ -63,7 +182,9 @@ impl Window {
                 },
                 glfw::WindowEvent::Key(key, _, action, _) => match action {
                     Action::Press => {
-                        self.keys_pressed.insert(key);
+                        if self.keys_pressed.insert(key) {
+                            self.keys_just_pressed.insert(key);
+                        }
                         if key == Key::Escape {
                             self.window_handle.set_should_close(true);
                         }
@@ -73,15 +194,215 @@ impl Window {
                     },
                     _ => {}
                 },
+                glfw::WindowEvent::MouseButton(button, action, _) => match action {
+                    Action::Press if self.mouse_buttons_pressed.insert(button) => {
+                        self.mouse_buttons_just_pressed.insert(button);
+                    },
+                    Action::Release => {
+                        self.mouse_buttons_pressed.remove(&button);
+                    },
+                    _ => {}
+                },
+                glfw::WindowEvent::CursorPos(x, y) => {
+                    // Skip the first event after (re)capturing so the cursor jump is not counted as movement
+                    if self.first_cursor_event {
+                        self.first_cursor_event = false;
+                    } else {
+                        self.cursor_delta.0 += x - self.cursor_pos.0;
+                        self.cursor_delta.1 += y - self.cursor_pos.1;
+                    }
+                    self.cursor_pos = (x, y);
+                },
+                glfw::WindowEvent::Scroll(x, y) => {
+                    self.scroll_delta.0 += x;
+                    self.scroll_delta.1 += y;
+                },
                 _ => {}
             }
         }
+
+        self.poll_gamepads();
+    }
+
+    // Gamepads are not event driven in GLFW, so their buttons and axes are sampled once per update
+    // Input from all connected gamepads is merged, so any of them can drive the player
+    fn poll_gamepads(&mut self) {
+        let mut pressed = HashSet::new();
+        let mut axes = [0.0; 6];
+        self.gamepads.clear();
+
+        for id in (0 ..= JoystickId::Joystick16 as i32).filter_map(JoystickId::from_i32) {
+            let joystick = self.glfw.get_joystick(id);
+            if !joystick.is_gamepad() {
+                continue;
+            }
+
+            let name = joystick.get_gamepad_name().unwrap_or_else(|| format!("{:?}", id));
+            self.gamepads.push((id, name));
+
+            if let Some(state) = joystick.get_gamepad_state() {
+                for button in (0 ..= GamepadButton::ButtonDpadLeft as i32).filter_map(GamepadButton::from_i32) {
+                    if state.get_button_state(button) == Action::Press {
+                        pressed.insert(button);
+                    }
+                }
+
+                let sticks = [
+                    (GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY),
+                    (GamepadAxis::AxisRightX, GamepadAxis::AxisRightY)
+                ];
+                for (axis_x, axis_y) in sticks {
+                    let (x, y) = apply_stick_deadzone(state.get_axis(axis_x), state.get_axis(axis_y), self.stick_deadzone);
+                    merge_axis(&mut axes[axis_x as usize], x);
+                    merge_axis(&mut axes[axis_y as usize], y);
+                }
+
+                for axis in [GamepadAxis::AxisLeftTrigger, GamepadAxis::AxisRightTrigger] {
+                    // Triggers rest at -1.0, remap them to 0.0 .. 1.0
+                    let value = apply_deadzone((state.get_axis(axis) + 1.0) * 0.5, self.trigger_deadzone);
+                    merge_axis(&mut axes[axis as usize], value);
+                }
+            }
+        }
+
+        self.gamepad_buttons_just_pressed = pressed.difference(&self.gamepad_buttons_pressed).copied().collect();
+        self.gamepad_buttons_pressed = pressed;
+        self.gamepad_axes = axes;
     }
 
     pub fn is_key_pressed(&self, key: Key) -> bool {
         self.keys_pressed.contains(&key)
     }
 
+    // True only on the update in which the key went down
+    pub fn is_key_just_pressed(&self, key: Key) -> bool {
+        self.keys_just_pressed.contains(&key)
+    }
+
+    pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_pressed.contains(&button)
+    }
+
+    pub fn is_mouse_button_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_just_pressed.contains(&button)
+    }
+
+    pub fn is_gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons_pressed.contains(&button)
+    }
+
+    pub fn is_gamepad_button_just_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons_just_pressed.contains(&button)
+    }
+
+    // Connected gamepads as (id, name)
+    pub fn get_gamepads(&self) -> &[(JoystickId, String)] {
+        &self.gamepads
+    }
+
+    // Axis value after the deadzone: sticks are -1.0 .. 1.0 (y is positive downwards), triggers 0.0 .. 1.0
+    pub fn get_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes[axis as usize]
+    }
+
+    pub fn set_gamepad_deadzones(&mut self, stick_deadzone: f32, trigger_deadzone: f32) {
+        self.stick_deadzone = stick_deadzone.clamp(0.0, 0.95);
+        self.trigger_deadzone = trigger_deadzone.clamp(0.0, 0.95);
+    }
+
+    // Movement input as (forward, right), each in -1.0 .. 1.0
+    // Digital actions give full speed, the left stick gives speed proportional to its deflection
+    pub fn get_movement_axes(&self) -> (f32, f32) {
+        let mut forward = -self.get_gamepad_axis(GamepadAxis::AxisLeftY);
+        let mut right = self.get_gamepad_axis(GamepadAxis::AxisLeftX);
+
+        if self.is_action_pressed(InputAction::MoveForward) { forward += 1.0; }
+        if self.is_action_pressed(InputAction::MoveBackward) { forward -= 1.0; }
+        if self.is_action_pressed(InputAction::MoveRight) { right += 1.0; }
+        if self.is_action_pressed(InputAction::MoveLeft) { right -= 1.0; }
+
+        (forward.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0))
+    }
+
+    // Rotation input in -1.0 .. 1.0, positive is anti-clockwise
+    // Digital actions give full turn rate, the right stick gives turn rate proportional to its deflection
+    pub fn get_rotation_axis(&self) -> f32 {
+        let mut turn = self.get_gamepad_axis(GamepadAxis::AxisRightX);
+
+        if self.is_action_pressed(InputAction::RotateAntiClockwise) { turn += 1.0; }
+        if self.is_action_pressed(InputAction::RotateClockwise) { turn -= 1.0; }
+
+        turn.clamp(-1.0, 1.0)
+    }
+
+    pub fn set_bindings(&mut self, bindings: InputBindings) {
+        self.bindings = bindings;
+    }
+
+    pub fn get_bindings(&self) -> &InputBindings {
+        &self.bindings
+    }
+
+    // True while any binding of the action is held
+    pub fn is_action_pressed(&self, action: InputAction) -> bool {
+        self.bindings.get(action).iter().any(|binding| match binding {
+            Binding::Key(key) => self.is_key_pressed(*key),
+            Binding::Mouse(button) => self.is_mouse_button_pressed(*button),
+            Binding::Gamepad(button) => self.is_gamepad_button_pressed(*button)
+        })
+    }
+
+    // True only on the update in which any binding of the action went down
+    pub fn is_action_just_pressed(&self, action: InputAction) -> bool {
+        self.bindings.get(action).iter().any(|binding| match binding {
+            Binding::Key(key) => self.is_key_just_pressed(*key),
+            Binding::Mouse(button) => self.is_mouse_button_just_pressed(*button),
+            Binding::Gamepad(button) => self.is_gamepad_button_just_pressed(*button)
+        })
+    }
+
+    // Cursor position in screen coordinates, relative to the top left corner of the window
+    pub fn get_cursor_pos(&self) -> (f64, f64) {
+        self.cursor_pos
+    }
+
+    // Cursor movement since the last update
+    pub fn get_cursor_delta(&self) -> (f64, f64) {
+        self.cursor_delta
+    }
+
+    // Scroll wheel offset since the last update (y is the regular vertical wheel)
+    pub fn get_scroll_delta(&self) -> (f64, f64) {
+        self.scroll_delta
+    }
+
+    // Captured cursor is hidden and locked to the window, which gives unbounded relative motion for mouse look
+    pub fn set_cursor_captured(&mut self, captured: bool) {
+        if captured {
+            self.window_handle.set_cursor_mode(CursorMode::Disabled);
+            if self.glfw.supports_raw_motion() {
+                self.window_handle.set_raw_mouse_motion(true);
+            }
+        } else {
+            if self.glfw.supports_raw_motion() {
+                self.window_handle.set_raw_mouse_motion(false);
+            }
+            self.window_handle.set_cursor_mode(CursorMode::Normal);
+        }
+
+        self.cursor_captured = captured;
+        self.first_cursor_event = true;
+        self.cursor_delta = (0.0, 0.0);
+    }
+
+    pub fn is_cursor_captured(&self) -> bool {
+        self.cursor_captured
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.window_handle.set_title(title);
+    }
+
     pub fn set_fps(&mut self, interval: i32) {
         self.window_handle.make_current();
         match interval {
@@ -101,4 +422,30 @@ impl Window {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Radial deadzone for a stick, the remaining range is rescaled so output still starts at 0.0
+fn apply_stick_deadzone(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= deadzone {
+        return (0.0, 0.0);
+    }
+
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone {
+        0.0
+    } else {
+        value.signum() * ((value.abs() - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+// Keep the value with the largest deflection when several gamepads are connected
+fn merge_axis(current: &mut f32, value: f32) {
+    if value.abs() > current.abs() {
+        *current = value;
+    }
+}
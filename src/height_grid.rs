@@ -0,0 +1,107 @@
+use crate::height_source::HeightSource;
+use crate::perlin_noise::grid_coords;
+
+// Height Grid
+// Row-major grid of heights sampled from a height source, the common format for exporters and terrain passes
+/* Example:
+// 512x512 samples starting at (10.0, 20.0), 0.05 world units apart
+let grid = HeightGrid::sample(&perlin_map, 10.0, 20.0, 0.05, 512, 512);
+let (min, max) = grid.min_max();
+
+// Terrain passes are applied in a chain
+grid.apply(&hydraulic_erosion).apply(&thermal_erosion);
+*/
+
+// A pass that changes the heights of a whole grid in place (erosion, filters)
+pub trait GridPass {
+    fn apply(&self, grid: &mut HeightGrid);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightGrid {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+    // Where the grid was sampled from, kept so results can be placed back in the world
+    origin_x: f32,
+    origin_y: f32,
+    step: f32
+}
+
+impl HeightGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        HeightGrid {
+            width,
+            height,
+            data: vec![0.0; width * height],
+            origin_x: 0.0,
+            origin_y: 0.0,
+            step: 1.0
+        }
+    }
+
+    pub fn from_data(width: usize, height: usize, data: Vec<f32>) -> Self {
+        assert_eq!(data.len(), width * height, "height grid data must hold width * height values");
+        HeightGrid { data, ..HeightGrid::new(width, height) }
+    }
+
+    // Sample a region of a source: width x height samples starting at (origin_x, origin_y), step world units apart
+    pub fn sample(source: &(impl HeightSource + ?Sized), origin_x: f32, origin_y: f32, step: f32, width: usize, height: usize) -> Self {
+        let mut data = vec![0.0; width * height];
+        source.fill_grid(&grid_coords(origin_x, step, width), &grid_coords(origin_y, step, height), &mut data);
+
+        HeightGrid { width, height, data, origin_x, origin_y, step }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn origin(&self) -> (f32, f32) {
+        (self.origin_x, self.origin_y)
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    pub fn set_placement(&mut self, origin_x: f32, origin_y: f32, step: f32) {
+        self.origin_x = origin_x;
+        self.origin_y = origin_y;
+        self.step = step;
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.data[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: f32) {
+        self.data[y * self.width + x] = value;
+    }
+
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [f32] {
+        &mut self.data
+    }
+
+    pub fn apply(&mut self, pass: &(impl GridPass + ?Sized)) -> &mut Self {
+        pass.apply(self);
+        self
+    }
+
+    // Lowest and highest height, (0.0, 0.0) for an empty grid
+    pub fn min_max(&self) -> (f32, f32) {
+        if self.data.is_empty() {
+            return (0.0, 0.0);
+        }
+
+        self.data.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &h| (min.min(h), max.max(h)))
+    }
+}
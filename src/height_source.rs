@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use crate::perlin_noise::PerlinMap;
+
+// Height Source
+// Anything that gives a terrain height for a world position (noise, imported heightmaps, filtered grids)
+/* Example:
+let z = source.height(x, y);
+
+let mut heights = vec![0.0; xs.len() * ys.len()];
+source.fill_grid(&xs, &ys, &mut heights);
+*/
+
+// A height source shared with the chunk worker threads
+pub type SharedHeightSource = Arc<dyn HeightSource + Send + Sync>;
+
+pub trait HeightSource {
+    fn height(&self, x: f32, y: f32) -> f32;
+
+    // out[row * xs.len() + col] = height(xs[col], ys[row]), sources with a faster batch path override this
+    fn fill_grid(&self, xs: &[f32], ys: &[f32], out: &mut [f32]) {
+        for (row, &y) in ys.iter().enumerate() {
+            for (col, &x) in xs.iter().enumerate() {
+                out[row * xs.len() + col] = self.height(x, y);
+            }
+        }
+    }
+
+    // Seed the heights were generated from, if there is one (written to export metadata)
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+
+    // Whether a plain_w x plain_h plain with its corner at (x, y) stays inside the source, unbounded sources accept everything
+    fn is_valid_coord(&self, _scale: f32, _plain_h: i32, _plain_w: i32, _x: f32, _y: f32) -> bool {
+        true
+    }
+}
+
+impl HeightSource for PerlinMap {
+    fn height(&self, x: f32, y: f32) -> f32 {
+        self.noise(x, y)
+    }
+
+    fn fill_grid(&self, xs: &[f32], ys: &[f32], out: &mut [f32]) {
+        self.noise_grid(xs, ys, out);
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.get_reproducible_seed()
+    }
+
+    fn is_valid_coord(&self, scale: f32, plain_h: i32, plain_w: i32, x: f32, y: f32) -> bool {
+        PerlinMap::is_valid_coord(self, scale, plain_h, plain_w, x, y)
+    }
+}
@@ -0,0 +1,374 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use crate::export::heightmap::{sidecar_path, HeightmapFormat};
+use crate::height_grid::HeightGrid;
+use crate::height_source::HeightSource;
+
+// Heightmap
+// Height source backed by an image (real or hand-painted elevation) instead of noise
+// Pixel (px, py) sits at world position origin + (px, py) * spacing, heights between pixels are interpolated
+// and positions outside the image take the height of the nearest edge
+/* Example:
+let mut heightmap = Heightmap::load("resources/heightmap.png").expect("Failed to load heightmap");
+heightmap.set_placement(0.0, 0.0, 0.05);
+heightmap.set_height_range(-0.5, 0.5);
+heightmap.set_sampling(Sampling::Bicubic);
+
+let z = heightmap.height(1.3, 2.7);
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    Nearest,
+    Bilinear, // Weighted average of the 4 closest pixels
+    Bicubic // Catmull-Rom spline through the 16 closest pixels, smooth slopes without the grid pattern of bilinear
+}
+
+#[derive(Clone)]
+pub struct Heightmap {
+    width: usize,
+    height: usize,
+    // Values as stored in the file, 0.0 .. 1.0 for integer formats
+    values: Vec<f32>,
+    normalized: bool,
+    // Heights the normalized values 0.0 and 1.0 are mapped to
+    min_height: f32,
+    max_height: f32,
+    origin_x: f32,
+    origin_y: f32,
+    spacing: f32,
+    sampling: Sampling,
+    // Seed of the noise the image was exported from, read from the sidecar
+    seed: Option<u64>
+}
+
+impl Heightmap {
+    // Values in 0.0 .. 1.0, mapped to the height range
+    pub fn from_normalized(width: usize, height: usize, values: Vec<f32>) -> Self {
+        assert_eq!(values.len(), width * height, "heightmap data must hold width * height values");
+        assert!(width > 0 && height > 0, "heightmap must not be empty");
+
+        Heightmap {
+            width,
+            height,
+            values,
+            normalized: true,
+            // About the range of the Perlin noise, so an image can replace it without retuning the camera
+            min_height: -0.5,
+            max_height: 0.5,
+            origin_x: 0.0,
+            origin_y: 0.0,
+            spacing: 1.0,
+            sampling: Sampling::Bilinear,
+            seed: None
+        }
+    }
+
+    // Heights used as they are, the height range is ignored
+    pub fn from_heights(width: usize, height: usize, heights: Vec<f32>) -> Self {
+        Heightmap { normalized: false, ..Heightmap::from_normalized(width, height, heights) }
+    }
+
+    // Keeps the heights and world placement of the grid
+    pub fn from_grid(grid: &HeightGrid) -> Self {
+        let mut heightmap = Heightmap::from_heights(grid.width(), grid.height(), grid.data().to_vec());
+        let (origin_x, origin_y) = grid.origin();
+        heightmap.set_placement(origin_x, origin_y, grid.step());
+        heightmap
+    }
+
+    // Seed reported for the heights, for images made from noise
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    // Load a grayscale PNG (8 or 16 bit, colour images use their luminance), a PGM (P2 or P5)
+    // or a raw f32/u16 file, picked by the file extension
+    // A JSON sidecar written by export_heightmap (<file>.json) restores the height range, placement and raw dimensions,
+    // raw files without one are assumed to be square
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Heightmap> {
+        let path = path.as_ref();
+        let format = HeightmapFormat::from_path(path)
+            .ok_or_else(|| invalid_data(format!("{}: unknown heightmap format", path.display())))?;
+        let sidecar = std::fs::read_to_string(sidecar_path(path)).ok();
+
+        let mut heightmap = match format {
+            HeightmapFormat::Png8 | HeightmapFormat::Png16 => load_png(path)?,
+            HeightmapFormat::Pgm8 | HeightmapFormat::Pgm16 => load_pgm(path)?,
+            HeightmapFormat::RawF32 | HeightmapFormat::RawU16 => {
+                let bytes_per_value = if format == HeightmapFormat::RawF32 { 4 } else { 2 };
+                let count = std::fs::metadata(path)?.len() as usize / bytes_per_value;
+
+                let (width, height) = match sidecar.as_deref().and_then(|json| Some((json_field(json, "width")?, json_field(json, "height")?))) {
+                    Some((width, height)) => (width, height),
+                    None => {
+                        let side = (count as f64).sqrt().round() as usize;
+                        (side, side)
+                    }
+                };
+                Heightmap::load_raw(path, width, height, format)?
+            },
+            HeightmapFormat::Pfm => return Err(invalid_data(format!("{}: PFM heightmaps cannot be loaded", path.display())))
+        };
+
+        if let Some(json) = sidecar {
+            if let (Some(min), Some(max)) = (json_field(&json, "min_height"), json_field(&json, "max_height")) {
+                heightmap.set_height_range(min, max);
+            }
+            if let (Some(x), Some(y), Some(scale)) = (json_field::<f32>(&json, "offset_x"), json_field::<f32>(&json, "offset_y"), json_field::<f32>(&json, "scale")) {
+                // Written by hand or by another tool, so checked here instead of tripping the assert in set_placement
+                if !(scale > 0.0 && scale.is_finite()) {
+                    return Err(invalid_data(format!("{}: sidecar scale must be positive, found {}", sidecar_path(path).display(), scale)));
+                }
+                heightmap.set_placement(x, y, scale);
+            }
+            heightmap.seed = json_field(&json, "seed");
+        }
+
+        Ok(heightmap)
+    }
+
+    // Headerless little-endian row-major data, RawF32 holds heights, RawU16 normalized values
+    pub fn load_raw(path: impl AsRef<Path>, width: usize, height: usize, format: HeightmapFormat) -> io::Result<Heightmap> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let bytes_per_value = match format {
+            HeightmapFormat::RawF32 => 4,
+            HeightmapFormat::RawU16 => 2,
+            _ => return Err(invalid_data(format!("{}: {} is not a raw format", path.display(), format.name())))
+        };
+
+        if width == 0 || height == 0 || bytes.len() != width * height * bytes_per_value {
+            return Err(invalid_data(format!("{}: expected {} x {} values, found {} bytes", path.display(), width, height, bytes.len())));
+        }
+
+        Ok(match format {
+            HeightmapFormat::RawF32 => {
+                let heights = bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+                Heightmap::from_heights(width, height, heights)
+            },
+            _ => {
+                let values = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0).collect();
+                Heightmap::from_normalized(width, height, values)
+            }
+        })
+    }
+
+    // Image size in pixels (width, height), not named height() to keep HeightSource::height free
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    // Heights of the darkest and brightest pixel, only used by normalized (integer) formats
+    pub fn set_height_range(&mut self, min_height: f32, max_height: f32) {
+        self.min_height = min_height;
+        self.max_height = max_height;
+    }
+
+    // World position of pixel (0, 0) and the world distance between two pixels, which must be positive
+    pub fn set_placement(&mut self, origin_x: f32, origin_y: f32, spacing: f32) {
+        assert!(spacing > 0.0, "heightmap pixel spacing must be positive");
+        self.origin_x = origin_x;
+        self.origin_y = origin_y;
+        self.spacing = spacing;
+    }
+
+    pub fn set_sampling(&mut self, sampling: Sampling) {
+        self.sampling = sampling;
+    }
+
+    pub fn get_sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    // World size of the image, from the first to the last pixel
+    pub fn extent(&self) -> (f32, f32) {
+        ((self.width - 1) as f32 * self.spacing, (self.height - 1) as f32 * self.spacing)
+    }
+
+    // Height of a pixel, coordinates are clamped to the image
+    pub fn pixel(&self, px: i64, py: i64) -> f32 {
+        let px = px.clamp(0, self.width as i64 - 1) as usize;
+        let py = py.clamp(0, self.height as i64 - 1) as usize;
+        let value = self.values[py * self.width + px];
+
+        if self.normalized {
+            self.min_height + value * (self.max_height - self.min_height)
+        } else {
+            value
+        }
+    }
+
+    // Height at a position in pixel units
+    fn sample(&self, fx: f32, fy: f32) -> f32 {
+        match self.sampling {
+            Sampling::Nearest => self.pixel(fx.round() as i64, fy.round() as i64),
+            Sampling::Bilinear => {
+                let (x0, y0) = (fx.floor(), fy.floor());
+                let (tx, ty) = (fx - x0, fy - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = lerp(self.pixel(x0, y0), self.pixel(x0 + 1, y0), tx);
+                let bottom = lerp(self.pixel(x0, y0 + 1), self.pixel(x0 + 1, y0 + 1), tx);
+                lerp(top, bottom, ty)
+            },
+            Sampling::Bicubic => {
+                let (x0, y0) = (fx.floor(), fy.floor());
+                let (tx, ty) = (fx - x0, fy - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let mut rows = [0.0; 4];
+                for (i, row) in rows.iter_mut().enumerate() {
+                    let y = y0 + i as i64 - 1;
+                    *row = catmull_rom(self.pixel(x0 - 1, y), self.pixel(x0, y), self.pixel(x0 + 1, y), self.pixel(x0 + 2, y), tx);
+                }
+                catmull_rom(rows[0], rows[1], rows[2], rows[3], ty)
+            }
+        }
+    }
+}
+
+impl HeightSource for Heightmap {
+    fn height(&self, x: f32, y: f32) -> f32 {
+        self.sample((x - self.origin_x) / self.spacing, (y - self.origin_y) / self.spacing)
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    // The plain has to stay on the image, including its last row and column
+    fn is_valid_coord(&self, scale: f32, plain_h: i32, plain_w: i32, x: f32, y: f32) -> bool {
+        let (extent_x, extent_y) = self.extent();
+        let end_x = x + ((plain_w - 1) as f32 * scale);
+        let end_y = y + ((plain_h - 1) as f32 * scale);
+
+        x >= self.origin_x && y >= self.origin_y && end_x <= self.origin_x + extent_x && end_y <= self.origin_y + extent_y
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Cubic through p1 (t = 0) and p2 (t = 1), with slopes taken from the neighbours p0 and p3
+fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let a = -0.5 * p0 + 1.5 * p1 - 1.5 * p2 + 0.5 * p3;
+    let b = p0 - 2.5 * p1 + 2.0 * p2 - 0.5 * p3;
+    let c = -0.5 * p0 + 0.5 * p2;
+    ((a * t + b) * t + c) * t + p1
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn load_png(path: &Path) -> io::Result<Heightmap> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    // Palettes and bit depths below 8 are expanded, 16 bit samples are kept
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = info.color_type.samples();
+    let sample_bytes = if info.bit_depth == png::BitDepth::Sixteen { 2 } else { 1 };
+    let max_value = if sample_bytes == 2 { 65535.0 } else { 255.0 };
+
+    let read_sample = |row: &[u8], index: usize| -> f32 {
+        let offset = index * sample_bytes;
+        let value = if sample_bytes == 2 { u16::from_be_bytes([row[offset], row[offset + 1]]) as f32 } else { row[offset] as f32 };
+        value / max_value
+    };
+
+    let mut values = Vec::with_capacity(width * height);
+    for row in buffer.chunks(info.line_size).take(height) {
+        for x in 0 .. width {
+            let first = x * channels;
+            let value = match info.color_type {
+                // Rec. 601 luma, alpha is ignored
+                png::ColorType::Rgb | png::ColorType::Rgba => {
+                    0.299 * read_sample(row, first) + 0.587 * read_sample(row, first + 1) + 0.114 * read_sample(row, first + 2)
+                },
+                _ => read_sample(row, first)
+            };
+            values.push(value);
+        }
+    }
+
+    Ok(Heightmap::from_normalized(width, height, values))
+}
+
+fn load_pgm(path: &Path) -> io::Result<Heightmap> {
+    let bytes = std::fs::read(path)?;
+    let error = |message: &str| invalid_data(format!("{}: {}", path.display(), message));
+
+    // Header: magic, width, height, maxval, separated by whitespace, # starts a comment
+    let mut tokens: Vec<String> = Vec::new();
+    let mut position = 0;
+    while tokens.len() < 4 && position < bytes.len() {
+        let c = bytes[position];
+        if c == b'#' {
+            while position < bytes.len() && bytes[position] != b'\n' {
+                position += 1;
+            }
+        } else if c.is_ascii_whitespace() {
+            position += 1;
+        } else {
+            let start = position;
+            while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            tokens.push(String::from_utf8_lossy(&bytes[start .. position]).into_owned());
+        }
+    }
+    if tokens.len() < 4 {
+        return Err(error("truncated PGM header"));
+    }
+
+    let parse = |token: &str| token.parse::<usize>().map_err(|_| error("invalid PGM header"));
+    let (width, height, max_value) = (parse(&tokens[1])?, parse(&tokens[2])?, parse(&tokens[3])?);
+    if width == 0 || height == 0 || max_value == 0 || max_value > 65535 {
+        return Err(error("invalid PGM header"));
+    }
+    let count = width * height;
+
+    let samples: Vec<usize> = match tokens[0].as_str() {
+        // Binary, a single whitespace byte separates the header from the data
+        "P5" => {
+            let data = &bytes[(position + 1).min(bytes.len()) ..];
+            if max_value < 256 {
+                data.iter().take(count).map(|&b| b as usize).collect()
+            } else {
+                data.chunks_exact(2).take(count).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).collect()
+            }
+        },
+        // Plain (ASCII) decimal values
+        "P2" => {
+            String::from_utf8_lossy(&bytes[position ..])
+                .split_ascii_whitespace()
+                .take(count)
+                .map(parse)
+                .collect::<io::Result<Vec<usize>>>()?
+        },
+        _ => return Err(error("only grayscale PGM (P2 or P5) is supported"))
+    };
+
+    if samples.len() != count {
+        return Err(error("truncated PGM data"));
+    }
+
+    let values = samples.into_iter().map(|v| v.min(max_value) as f32 / max_value as f32).collect();
+    Ok(Heightmap::from_normalized(width, height, values))
+}
+
+// Number stored under a key of the flat JSON sidecar, e.g. "min_height": -0.45
+fn json_field<T: std::str::FromStr>(json: &str, key: &str) -> Option<T> {
+    let start = json.find(&format!("\"{}\"", key))? + key.len() + 2;
+    let rest = json[start ..].trim_start().strip_prefix(':')?;
+    let end = rest.find([',', '}', '\n']).unwrap_or(rest.len());
+    rest[.. end].trim().parse().ok()
+}
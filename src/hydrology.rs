@@ -0,0 +1,350 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+use serde::Deserialize;
+
+use crate::height_grid::{GridPass, HeightGrid};
+
+// Hydrology
+// Rivers and lakes that follow the terrain: depressions are filled into lakes (priority flood), every cell passes
+// its rain downhill (D8 to the steepest neighbour, or D-infinity split between two neighbours) and the accumulated flow
+// carves channels where it is above a threshold
+// The result holds the river polylines and a water mask for the renderer and exporters
+/* Example:
+let rivers = Rivers { threshold: 200.0, ..Default::default() };
+let network = rivers.generate(&mut grid);
+
+export_rivers(&network, "rivers.geojson")?;
+export_heightmap(&network.water_mask, "water.png", HeightmapFormat::Png8, &HeightmapMetadata::from_grid(&network.water_mask, None))?;
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowMethod {
+    D8, // All flow to the steepest of the 8 neighbours, narrow single cell rivers
+    DInfinity // Split between the two neighbours around the steepest direction, smoother flow on slopes
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rivers {
+    pub method: FlowMethod,
+    pub threshold: f32, // Cells draining into a cell before it is a river
+    pub depth: f32, // Channel depth at the threshold, grows with the square root of the flow
+    pub max_depth: f32,
+    pub sea_level: Option<f32>, // Rivers end in the sea below it, and it is not filled into lakes
+    pub min_lake_depth: f32 // Shallower filled depressions are only drained, not turned into lakes
+}
+
+impl Default for Rivers {
+    fn default() -> Self {
+        Rivers {
+            method: FlowMethod::D8,
+            threshold: 100.0,
+            depth: 0.005,
+            max_depth: 0.03,
+            sea_level: Some(0.0),
+            min_lake_depth: 0.002
+        }
+    }
+}
+
+pub struct RiverNetwork {
+    // Downstream points of every river in world coordinates, z is the water surface
+    // A tributary ends on the point where it joins
+    pub polylines: Vec<Vec<[f32; 3]>>,
+    // 1.0 on river and lake cells, 0.0 elsewhere (sea cells are not included), same placement as the grid
+    pub water_mask: HeightGrid,
+    // Height of the water surface on river and lake cells, of the ground elsewhere
+    pub surface: HeightGrid,
+    // Water depth on river and lake cells, 0.0 elsewhere
+    pub depth: HeightGrid,
+    pub flow: FlowField
+}
+
+impl RiverNetwork {
+    // Water surface over the river and lake cells for the renderer: x, y, surface height, water depth per vertex
+    // in world coordinates, a triangle pair for every cell with a wet corner, the dry corners have depth 0.0 (the shoreline)
+    pub fn water_mesh(&self) -> (Vec<f32>, Vec<i32>) {
+        let (width, height) = (self.surface.width(), self.surface.height());
+        let (origin_x, origin_y) = self.surface.origin();
+        let step = self.surface.step();
+
+        let mut vertices = Vec::with_capacity(width * height * 4);
+        for y in 0 .. height {
+            for x in 0 .. width {
+                vertices.extend_from_slice(&[origin_x + x as f32 * step, origin_y + y as f32 * step, self.surface.get(x, y), self.depth.get(x, y)]);
+            }
+        }
+
+        let mut indices = Vec::new();
+        let wet = |x: usize, y: usize| self.water_mask.get(x, y) > 0.0;
+        for y in 0 .. height.saturating_sub(1) {
+            for x in 0 .. width.saturating_sub(1) {
+                if wet(x, y) || wet(x + 1, y) || wet(x, y + 1) || wet(x + 1, y + 1) {
+                    let i = (y * width + x) as i32;
+                    let w = width as i32;
+                    indices.extend_from_slice(&[i, i + 1, i + w, i + 1, i + w + 1, i + w]);
+                }
+            }
+        }
+
+        (vertices, indices)
+    }
+}
+
+// Where every cell sends its flow and how much passes through it
+pub struct FlowField {
+    width: usize,
+    height: usize,
+    // Receiving cells and the share of the flow each gets, empty for outlets (edge of the grid, sea)
+    receivers: Vec<Vec<(usize, f32)>>,
+    accumulation: Vec<f32>
+}
+
+impl FlowField {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn receivers(&self, x: usize, y: usize) -> &[(usize, f32)] {
+        &self.receivers[y * self.width + x]
+    }
+
+    // Number of cells (including itself) draining through a cell
+    pub fn accumulation(&self, x: usize, y: usize) -> f32 {
+        self.accumulation[y * self.width + x]
+    }
+
+    // The receiver that gets most of the flow
+    fn main_receiver(&self, i: usize) -> Option<usize> {
+        self.receivers[i].iter().max_by(|a, b| a.1.total_cmp(&b.1)).map(|(r, _)| *r)
+    }
+}
+
+impl Rivers {
+    // Carves the river channels into the grid and returns the network, lake beds keep their height under the water surface
+    pub fn generate(&self, grid: &mut HeightGrid) -> RiverNetwork {
+        let (width, height) = (grid.width(), grid.height());
+        let lakes = fill_depressions(grid, 0.0, self.sea_level);
+        // A tiny slope across filled flats so every cell has somewhere to drain
+        let drained = fill_depressions(grid, 1e-5, self.sea_level);
+        let flow = flow_field(&drained, self.method, self.sea_level);
+
+        let mut water_mask = HeightGrid::new(width, height);
+        let mut surface = grid.clone();
+        let (origin_x, origin_y) = grid.origin();
+        water_mask.set_placement(origin_x, origin_y, grid.step());
+
+        let is_sea = |h: f32| self.sea_level.is_some_and(|level| h < level);
+        let mut is_river = vec![false; width * height];
+        for (i, (&lake, &drained)) in lakes.data().iter().zip(drained.data()).enumerate() {
+            let ground = grid.data()[i];
+            if is_sea(ground) {
+                continue;
+            }
+
+            if lake - ground > self.min_lake_depth {
+                water_mask.data_mut()[i] = 1.0;
+                surface.data_mut()[i] = lake;
+            } else if flow.accumulation[i] >= self.threshold {
+                // The channel is cut into the drained surface, so the bed keeps falling downstream
+                let depth = (self.depth * (flow.accumulation[i] / self.threshold).sqrt()).min(self.max_depth);
+                let bed = drained - depth;
+                grid.data_mut()[i] = bed.min(ground);
+                surface.data_mut()[i] = bed.min(ground) + depth * 0.7;
+                water_mask.data_mut()[i] = 1.0;
+                is_river[i] = true;
+            }
+        }
+
+        let mut depth = surface.clone();
+        for (d, ground) in depth.data_mut().iter_mut().zip(grid.data()) {
+            *d -= ground;
+        }
+
+        let polylines = trace_rivers(&flow, &is_river, &surface);
+        RiverNetwork { polylines, water_mask, surface, depth, flow }
+    }
+}
+
+impl GridPass for Rivers {
+    fn apply(&self, grid: &mut HeightGrid) {
+        self.generate(grid);
+    }
+}
+
+// Neighbour offsets counter-clockwise from east, cardinal on even indices
+const NEIGHBOURS: [(i64, i64); 8] = [(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1), (0, 1), (1, 1)];
+
+fn neighbour(width: usize, height: usize, i: usize, direction: usize) -> Option<usize> {
+    let (dx, dy) = NEIGHBOURS[direction % 8];
+    let (x, y) = ((i % width) as i64 + dx, (i / width) as i64 + dy);
+    (x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height).then(|| y as usize * width + x as usize)
+}
+
+// Ordered by height for the priority queue
+#[derive(PartialEq)]
+struct Cell(f32, usize);
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+// Priority flood: raises every depression to the height where it spills over, so all cells drain to the edge of the grid
+// (or the sea), epsilon > 0.0 also makes each cell at least that much higher than the one it drains to
+pub fn fill_depressions(grid: &HeightGrid, epsilon: f32, sea_level: Option<f32>) -> HeightGrid {
+    let (width, height) = (grid.width(), grid.height());
+    let mut filled = grid.clone();
+    let mut done = vec![false; width * height];
+    let mut queue = BinaryHeap::new();
+
+    // The water leaves at the edge and into the sea
+    for (i, &h) in grid.data().iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        let outlet = x == 0 || y == 0 || x == width - 1 || y == height - 1 || sea_level.is_some_and(|level| h < level);
+        if outlet {
+            done[i] = true;
+            queue.push(Reverse(Cell(h, i)));
+        }
+    }
+
+    // Grow inwards from the lowest known cell, a neighbour below it is in a depression and is raised to its level
+    while let Some(Reverse(Cell(h, i))) = queue.pop() {
+        for direction in 0 .. 8 {
+            if let Some(n) = neighbour(width, height, i, direction) {
+                if !done[n] {
+                    done[n] = true;
+                    let level = filled.data()[n].max(h + epsilon);
+                    filled.data_mut()[n] = level;
+                    queue.push(Reverse(Cell(level, n)));
+                }
+            }
+        }
+    }
+
+    filled
+}
+
+// Flow directions and accumulation over a grid without depressions (see fill_depressions)
+pub fn flow_field(filled: &HeightGrid, method: FlowMethod, sea_level: Option<f32>) -> FlowField {
+    let (width, height) = (filled.width(), filled.height());
+    let map = filled.data();
+    let step = filled.step();
+
+    let receivers: Vec<Vec<(usize, f32)>> = (0 .. width * height).map(|i| {
+        if sea_level.is_some_and(|level| map[i] < level) {
+            return Vec::new();
+        }
+
+        match method {
+            FlowMethod::D8 => {
+                let slope = |direction: usize, n: usize| (map[i] - map[n]) / if direction.is_multiple_of(2) { step } else { step * SQRT_2 };
+                (0 .. 8)
+                    .filter_map(|direction| neighbour(width, height, i, direction).map(|n| (n, slope(direction, n))))
+                    .filter(|(_, slope)| *slope > 0.0)
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(n, _)| vec![(n, 1.0)])
+                    .unwrap_or_default()
+            },
+            FlowMethod::DInfinity => d_infinity(map, width, height, step, i)
+        }
+    }).collect();
+
+    // Highest cells first, every cell has all its inflow before passing it on
+    let mut order: Vec<usize> = (0 .. width * height).collect();
+    order.sort_by(|a, b| map[*b].total_cmp(&map[*a]));
+
+    let mut accumulation = vec![1.0; width * height];
+    for i in order {
+        for &(r, share) in receivers[i].iter() {
+            accumulation[r] += accumulation[i] * share;
+        }
+    }
+
+    FlowField { width, height, receivers, accumulation }
+}
+
+// Tarboton's D-infinity: the steepest direction over the 8 triangular facets around the cell,
+// split between the cardinal and diagonal neighbour of that facet by angle
+fn d_infinity(map: &[f32], width: usize, height: usize, step: f32, i: usize) -> Vec<(usize, f32)> {
+    let mut best: Option<(f32, Vec<(usize, f32)>)> = None;
+
+    for facet in 0usize .. 8 {
+        // Facets alternate their orientation, each one has a cardinal and the next or previous diagonal neighbour
+        let cardinal = (facet / 2) * 2;
+        let diagonal = if facet.is_multiple_of(2) { (cardinal + 1) % 8 } else { (cardinal + 7) % 8 };
+        let (Some(e1), Some(e2)) = (neighbour(width, height, i, cardinal), neighbour(width, height, i, diagonal)) else {
+            continue;
+        };
+
+        let s1 = (map[i] - map[e1]) / step;
+        let s2 = (map[e1] - map[e2]) / step;
+        let (mut angle, mut slope) = (s2.atan2(s1), s1.hypot(s2));
+        if angle < 0.0 {
+            (angle, slope) = (0.0, s1);
+        } else if angle > FRAC_PI_4 {
+            (angle, slope) = (FRAC_PI_4, (map[i] - map[e2]) / (step * SQRT_2));
+        }
+
+        if slope > 0.0 && best.as_ref().is_none_or(|(best_slope, _)| slope > *best_slope) {
+            let share = angle / FRAC_PI_4;
+            let split = [(e1, 1.0 - share), (e2, share)].into_iter().filter(|(_, s)| *s > 0.0).collect();
+            best = Some((slope, split));
+        }
+    }
+
+    best.map(|(_, split)| split).unwrap_or_default()
+}
+
+// Follows every river from its head downstream, stopping where it leaves the river cells or joins a traced river
+fn trace_rivers(flow: &FlowField, is_river: &[bool], surface: &HeightGrid) -> Vec<Vec<[f32; 3]>> {
+    let width = flow.width;
+    let (origin_x, origin_y) = surface.origin();
+    let point = |i: usize| [origin_x + (i % width) as f32 * surface.step(), origin_y + (i / width) as f32 * surface.step(), surface.data()[i]];
+
+    // A head is a river cell no other river cell flows into
+    let mut has_upstream = vec![false; is_river.len()];
+    for i in (0 .. is_river.len()).filter(|i| is_river[*i]) {
+        if let Some(r) = flow.main_receiver(i) {
+            has_upstream[r] = true;
+        }
+    }
+
+    let mut visited = vec![false; is_river.len()];
+    let mut polylines = Vec::new();
+    for head in (0 .. is_river.len()).filter(|i| is_river[*i] && !has_upstream[*i]) {
+        let mut polyline = vec![point(head)];
+        visited[head] = true;
+
+        let mut i = head;
+        while let Some(next) = flow.main_receiver(i) {
+            polyline.push(point(next));
+            if visited[next] || !is_river[next] {
+                break;
+            }
+            visited[next] = true;
+            i = next;
+        }
+
+        if polyline.len() > 1 {
+            polylines.push(polyline);
+        }
+    }
+
+    polylines
+}
@@ -0,0 +1,35 @@
+// Terrain Generator
+// Perlin noise terrain, height grids, importers and exporters, and the chunked mesh generation behind the viewer
+// The graphics wrappers (GLFW window, OpenGL buffers and shaders) need the viewer feature, which is on by default
+/* Example:
+use terrain_generator::perlin_noise::PerlinMap;
+use terrain_generator::height_grid::HeightGrid;
+
+let mut perlin_map = PerlinMap::new();
+perlin_map.set_hashed(42);
+let grid = HeightGrid::sample(&perlin_map, 0.0, 0.0, 0.05, 512, 512);
+*/
+
+pub mod perlin_noise;
+pub mod fractal_noise;
+pub mod height_source;
+pub mod height_grid;
+pub mod erosion;
+pub mod filters;
+pub mod heightmap;
+pub mod shaping;
+pub mod water;
+pub mod hydrology;
+pub mod biomes;
+pub mod scatter;
+pub mod culling;
+pub mod functions;
+pub mod chunks;
+pub mod export;
+pub mod config;
+pub mod cli;
+pub mod structs;
+pub mod timestep;
+
+#[cfg(feature = "viewer")]
+pub mod graphics;
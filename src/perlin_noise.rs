@@ -1,11 +1,36 @@
-use rand::{Rng, thread_rng};
+use rand::{Rng, SeedableRng, thread_rng};
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+
+// Samples evaluated together by noise_grid, sized so the arithmetic maps onto SIMD registers (8 x f32 = 256 bit)
+const LANES: usize = 8;
+
+// Where the gradient angle of a lattice point comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientMode {
+    // One stored angle per cell of vec_map, clamped or wrapped outside of width x height (editable)
+    Stored,
+    // Angle computed from a seeded hash of the lattice coordinates, infinite and non-repeating
+    Hashed
+}
 
 #[derive(Debug)]
 pub struct PerlinMap {
     vec_map: Vec<i32>,
     width: i32,
     height: i32,
-    seamless: bool
+    seamless: bool,
+    mode: GradientMode,
+    seed: u64,
+    // The stored map was generated from seed and can be reproduced
+    seeded_map: bool,
+    angle_offset: i32
+}
+
+impl Default for PerlinMap {
+    fn default() -> Self {
+        PerlinMap::new()
+    }
 }
 
 impl PerlinMap {
@@ -14,34 +39,86 @@ impl PerlinMap {
             vec_map: vec![60, 45, 150, 240],
             width: 2,
             height: 2,
-            seamless: false
+            seamless: false,
+            mode: GradientMode::Stored,
+            seed: 0,
+            seeded_map: false,
+            angle_offset: 0
         }
     }
 
+    // Switch to hashed gradients, the same seed always gives the same terrain
+    pub fn set_hashed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.mode = GradientMode::Hashed;
+    }
+
+    pub fn set_mode(&mut self, mode: GradientMode) {
+        self.mode = mode;
+    }
+
+    pub fn get_mode(&self) -> GradientMode {
+        self.mode
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn set_seamless(&mut self, seamless: bool) {
         self.seamless = seamless;
     }
 
     pub fn generate_vec_map(&mut self, width: i32, height: i32) {
+        self.fill_vec_map(width, height, &mut thread_rng());
+        self.seeded_map = false;
+    }
+
+    // Same as generate_vec_map, but the same seed always gives the same map
+    pub fn generate_seeded_vec_map(&mut self, width: i32, height: i32, seed: u64) {
+        self.fill_vec_map(width, height, &mut StdRng::seed_from_u64(seed));
+        self.seed = seed;
+        self.seeded_map = true;
+    }
+
+    fn fill_vec_map(&mut self, width: i32, height: i32, rng: &mut impl Rng) {
         self.width = width;
         self.height = height;
 
-        let mut rng = thread_rng();
         let mut map: Vec<i32> = Vec::new();
         for _ in 0 .. width * height {
             map.push(rng.gen_range(0 .. 360));
         }
 
         self.vec_map = map;
+        self.mode = GradientMode::Stored;
+    }
+
+    // Seed the current gradients can be reproduced from, None for a map from generate_vec_map
+    pub fn get_reproducible_seed(&self) -> Option<u64> {
+        match self.mode {
+            GradientMode::Hashed => Some(self.seed),
+            GradientMode::Stored if self.seeded_map => Some(self.seed),
+            GradientMode::Stored => None
+        }
     }
 
     pub fn rotate_vec_map(&mut self, angle: i32) {
         for v in self.vec_map.iter_mut() {
             *v = (*v + angle) % 360;
         }
+        // Hashed angles are not stored, so their rotation is kept as an offset
+        self.angle_offset = (self.angle_offset + angle) % 360;
     }
 
     fn gradient_angle(&self, x: i32, y: i32) -> i32 {
+        match self.mode {
+            GradientMode::Stored => self.stored_gradient_angle(x, y),
+            GradientMode::Hashed => (hash_lattice(x, y, self.seed) % 360) as i32 + self.angle_offset
+        }
+    }
+
+    fn stored_gradient_angle(&self, x: i32, y: i32) -> i32 {
         //let index = y * self.width + x;
         //self.vec_map[index as usize]
         let calc_x ;
@@ -78,11 +155,6 @@ impl PerlinMap {
         (gx * dx) + (gy * dy)
     }
 
-    fn fade(&self, t: f32) -> f32 {
-        // 6t^5 - 15t^4 + 10t^3
-        t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
-    }
-
     fn lerp(&self, a: f32, b: f32, t: f32) -> f32 {
         // Linear interpolation
         (1.0 - t) * a + t * b
@@ -106,10 +178,7 @@ impl PerlinMap {
         let sx = x - x0 as f32;
         let sy = y - y0 as f32;
 
-        // Fade curves for x and y
-        // sx/sy: no fade, u/v: fade
-        let u = self.fade(sx);
-        let v = self.fade(sy);
+        // Interpolated on sx/sy directly, without a fade curve (6t^5 - 15t^4 + 10t^3)
         
         // Interpolate along x for top and bottom edges
         let nx0 = self.lerp(n00, n10, sx);
@@ -130,9 +199,118 @@ impl PerlinMap {
         self.lerp(nx0, nx1, sy)
     }
 
+    // Gradient vector of a lattice point, the same values dot_product computes on the fly
+    fn gradient(&self, ix: i32, iy: i32) -> (f32, f32) {
+        let theta_rad = (self.gradient_angle(ix, iy) as f32).to_radians();
+        (theta_rad.cos(), theta_rad.sin())
+    }
+
+    // Batch version of noise for a whole grid: out[row * xs.len() + col] = noise(xs[col], ys[row])
+    // Gives the same values as calling noise per sample, but gradient vectors are computed once per lattice point,
+    // per column work is shared by all rows, samples are processed in SIMD friendly lanes and rows run in parallel
+    pub fn noise_grid(&self, xs: &[f32], ys: &[f32], out: &mut [f32]) {
+        let width = xs.len();
+        assert_eq!(out.len(), width * ys.len(), "noise_grid output must hold xs.len() * ys.len() samples");
+        if width == 0 || ys.is_empty() {
+            return;
+        }
+
+        // Lattice columns and rows the samples touch, every x0 and x0 + 1 (and y0 and y0 + 1) once, in order
+        // Only these get a gradient, so sparse samples (a large scale) cost no more than dense ones
+        // As they are sorted whole numbers without duplicates, x0 + 1 always follows x0
+        let lattice_xs = lattice_coords(xs);
+        let lattice_ys = lattice_coords(ys);
+        let lattice_w = lattice_xs.len();
+        let lattice_index = |lattice: &[i32], v: f32| lattice.binary_search(&(v.floor() as i32)).expect("lattice holds every sample cell");
+
+        // Precomputed gradient vectors, stored as separate x and y planes
+        let mut grad_x = vec![0.0f32; lattice_w * lattice_ys.len()];
+        let mut grad_y = vec![0.0f32; lattice_w * lattice_ys.len()];
+        grad_x
+            .par_chunks_mut(lattice_w)
+            .zip(grad_y.par_chunks_mut(lattice_w))
+            .zip(lattice_ys.par_iter())
+            .for_each(|((gx_row, gy_row), &iy)| {
+                for (col, &ix) in lattice_xs.iter().enumerate() {
+                    let (gx, gy) = self.gradient(ix, iy);
+                    gx_row[col] = gx;
+                    gy_row[col] = gy;
+                }
+            });
+
+        // Per column data shared by every row
+        let mut col_cell = Vec::with_capacity(width);
+        let mut col_dx0 = Vec::with_capacity(width);
+        let mut col_dx1 = Vec::with_capacity(width);
+        let mut col_sx = Vec::with_capacity(width);
+        for &x in xs {
+            let x0 = x.floor() as i32;
+            col_cell.push(lattice_index(&lattice_xs, x));
+            col_dx0.push(x0 as f32 - x);
+            col_dx1.push((x0 + 1) as f32 - x);
+            col_sx.push(x - x0 as f32);
+        }
+
+        // Small grids (chunks) stay on fewer threads, splitting them finer costs more than it saves
+        let min_rows = (4096 / width).max(1);
+
+        out.par_chunks_mut(width)
+            .with_min_len(min_rows)
+            .zip(ys.par_iter())
+            .for_each(|(out_row, &y)| {
+                let y0 = y.floor() as i32;
+                let dy0 = y0 as f32 - y;
+                let dy1 = (y0 + 1) as f32 - y;
+                let sy = y - y0 as f32;
+
+                let top = lattice_index(&lattice_ys, y) * lattice_w;
+                let bottom = top + lattice_w;
+
+                let mut col = 0;
+                while col < width {
+                    let lanes = LANES.min(width - col);
+
+                    // Gather the four corner gradients for each lane
+                    let mut g00 = [(0.0f32, 0.0f32); LANES];
+                    let mut g10 = [(0.0f32, 0.0f32); LANES];
+                    let mut g01 = [(0.0f32, 0.0f32); LANES];
+                    let mut g11 = [(0.0f32, 0.0f32); LANES];
+                    let mut dx0 = [0.0f32; LANES];
+                    let mut dx1 = [0.0f32; LANES];
+                    let mut sx = [0.0f32; LANES];
+                    for lane in 0 .. lanes {
+                        let cell = col_cell[col + lane];
+                        g00[lane] = (grad_x[top + cell], grad_y[top + cell]);
+                        g10[lane] = (grad_x[top + cell + 1], grad_y[top + cell + 1]);
+                        g01[lane] = (grad_x[bottom + cell], grad_y[bottom + cell]);
+                        g11[lane] = (grad_x[bottom + cell + 1], grad_y[bottom + cell + 1]);
+                        dx0[lane] = col_dx0[col + lane];
+                        dx1[lane] = col_dx1[col + lane];
+                        sx[lane] = col_sx[col + lane];
+                    }
+
+                    // Same arithmetic as dot_product and noise, on full lanes so it vectorizes
+                    let mut result = [0.0f32; LANES];
+                    for lane in 0 .. LANES {
+                        let n00 = (g00[lane].0 * dx0[lane]) + (g00[lane].1 * dy0);
+                        let n10 = (g10[lane].0 * dx1[lane]) + (g10[lane].1 * dy0);
+                        let n01 = (g01[lane].0 * dx0[lane]) + (g01[lane].1 * dy1);
+                        let n11 = (g11[lane].0 * dx1[lane]) + (g11[lane].1 * dy1);
+
+                        let nx0 = (1.0 - sx[lane]) * n00 + sx[lane] * n10;
+                        let nx1 = (1.0 - sx[lane]) * n01 + sx[lane] * n11;
+                        result[lane] = (1.0 - sy) * nx0 + sy * nx1;
+                    }
+
+                    out_row[col .. col + lanes].copy_from_slice(&result[.. lanes]);
+                    col += lanes;
+                }
+            });
+    }
+
     pub fn is_valid_coord(&self, scale: f32, plain_h: i32, plain_w: i32, x: f32, y: f32) -> bool {
-        if self.seamless {
-            // In seamless mode, all coordinates are valid
+        if self.seamless || self.mode == GradientMode::Hashed {
+            // In seamless and hashed mode, all coordinates are valid
             return true;
         }
         
@@ -146,6 +324,33 @@ impl PerlinMap {
             return false;
         }
         
-        return true;
+        true
     }
-}
\ No newline at end of file
+}
+
+// Seeded integer hash of a lattice point (SplitMix64 finalizer)
+// Every (x, y) in the i32 range gets its own value, so the pattern never repeats
+fn hash_lattice(x: i32, y: i32, seed: u64) -> u64 {
+    let mut h = seed
+        ^ (x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F).rotate_left(32);
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+// Sorted lattice coordinates of the cells holding the samples, with the right (or bottom) neighbour of each
+fn lattice_coords(coords: &[f32]) -> Vec<i32> {
+    let mut lattice: Vec<i32> = coords.iter().flat_map(|&v| {
+        let v0 = v.floor() as i32;
+        [v0, v0 + 1]
+    }).collect();
+    lattice.sort_unstable();
+    lattice.dedup();
+    lattice
+}
+
+// Evenly spaced sample coordinates for noise_grid: origin + i * step (the same expression generate_mesh uses)
+pub fn grid_coords(origin: f32, step: f32, count: usize) -> Vec<f32> {
+    (0 .. count).map(|i| (i as f32 * step) + origin).collect()
+}
@@ -0,0 +1,186 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::biomes::{Biome, BiomeMap};
+use crate::height_source::HeightSource;
+
+// Object Scattering
+// Places trees, bushes and rocks on the terrain: every layer spreads candidates over square tiles with
+// Poisson-disk sampling (no two closer than the spacing), keeps a share of them and drops the ones outside its
+// biomes, height range or slope, and snaps the rest to the terrain height
+// Every tile has its own seed, so a tile always gets the same objects no matter which chunk asks for it
+// Candidates of neighbouring tiles are not checked against each other and can come closer along the tile edges
+/* Example:
+let mut trees = ScatterLayer::default();
+trees.biomes = vec![Biome::TemperateForest, Biome::Taiga];
+trees.max_slope = 30.0;
+let scatter = Scatter::new(42, vec![trees]);
+
+// Objects of the 0.8 x 0.8 tile at (3, -2)
+let instances = scatter.scatter_tile(&perlin_map, Some(&biome_map), 3, -2, 0.8, 0);
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterLayer {
+    pub spacing: f32, // Smallest distance between two objects in world units
+    pub density: f32, // Share of the Poisson-disk candidates kept, 0.0 .. 1.0
+    pub biomes: Vec<Biome>, // Biomes the objects grow in, empty for everywhere (needs a biome map otherwise)
+    pub min_height: f32,
+    pub max_height: f32,
+    pub max_slope: f32, // Steepest ground in degrees
+    pub min_scale: f32, // Size of an object is picked between min_scale and max_scale
+    pub max_scale: f32,
+    pub max_level: u32 // Coarsest chunk level (see chunks) that still shows the layer, so small objects can disappear sooner
+}
+
+impl Default for ScatterLayer {
+    fn default() -> Self {
+        ScatterLayer {
+            spacing: 0.1,
+            density: 1.0,
+            biomes: Vec::new(),
+            min_height: 0.0,
+            max_height: f32::INFINITY,
+            max_slope: 35.0,
+            min_scale: 0.04,
+            max_scale: 0.08,
+            max_level: 1
+        }
+    }
+}
+
+// One placed object, position in world units with z on the terrain, rotation in radians around z
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    pub layer: usize,
+    pub position: [f32; 3],
+    pub rotation: f32,
+    pub scale: f32
+}
+
+pub struct Scatter {
+    seed: u64,
+    layers: Vec<ScatterLayer>
+}
+
+impl Scatter {
+    pub fn new(seed: u64, layers: Vec<ScatterLayer>) -> Self {
+        Scatter { seed, layers }
+    }
+
+    pub fn get_layers(&self) -> &[ScatterLayer] {
+        &self.layers
+    }
+
+    // Objects in the tile from (tile_x, tile_y) * tile_size to (tile_x + 1, tile_y + 1) * tile_size
+    // Only the layers shown at the chunk level are scattered, level 0 gives all of them
+    pub fn scatter_tile(&self, source: &(impl HeightSource + ?Sized), biomes: Option<&BiomeMap>, tile_x: i32, tile_y: i32, tile_size: f32, level: u32) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        let (origin_x, origin_y) = (tile_x as f32 * tile_size, tile_y as f32 * tile_size);
+
+        for (index, layer) in self.layers.iter().enumerate().filter(|(_, layer)| layer.max_level >= level) {
+            let mut rng = StdRng::seed_from_u64(tile_seed(self.seed, index, tile_x, tile_y));
+
+            for [x, y] in poisson_disk(&mut rng, tile_size, layer.spacing) {
+                let (x, y) = (origin_x + x, origin_y + y);
+                // Drawn for every candidate so the kept objects do not shift when a filter changes
+                let (keep, rotation, scale) = (rng.gen::<f32>(), rng.gen_range(0.0 .. std::f32::consts::TAU), rng.gen::<f32>());
+                if keep >= layer.density {
+                    continue;
+                }
+
+                let z = source.height(x, y);
+                if z < layer.min_height || z > layer.max_height || slope(source, x, y, layer.spacing) > layer.max_slope {
+                    continue;
+                }
+                if !layer.biomes.is_empty() && !biomes.is_some_and(|biomes| layer.biomes.contains(&biomes.classify(x, y, z).primary)) {
+                    continue;
+                }
+
+                let scale = layer.min_scale + (layer.max_scale - layer.min_scale) * scale;
+                instances.push(Instance { layer: index, position: [x, y, z], rotation, scale });
+            }
+        }
+
+        instances
+    }
+}
+
+// Slope of the ground in degrees, from central differences over a fraction of the spacing
+fn slope(source: &(impl HeightSource + ?Sized), x: f32, y: f32, spacing: f32) -> f32 {
+    let d = (spacing * 0.25).max(1e-4);
+    let dx = (source.height(x + d, y) - source.height(x - d, y)) / (2.0 * d);
+    let dy = (source.height(x, y + d) - source.height(x, y - d)) / (2.0 * d);
+    dx.hypot(dy).atan().to_degrees()
+}
+
+// Mixes the seed, layer and tile into the seed of one tile (SplitMix64 finalizer)
+fn tile_seed(seed: u64, layer: usize, tile_x: i32, tile_y: i32) -> u64 {
+    let mut z = seed
+        ^ (layer as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (tile_x as u32 as u64).wrapping_mul(0xBF58_476D_1CE4_E5B9)
+        ^ (tile_y as u32 as u64).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Bridson's algorithm: points in [0, size) x [0, size), no two closer than spacing
+fn poisson_disk(rng: &mut StdRng, size: f32, spacing: f32) -> Vec<[f32; 2]> {
+    const ATTEMPTS: usize = 30;
+    if size <= 0.0 || spacing <= 0.0 {
+        return Vec::new();
+    }
+
+    // A cell holds at most one point
+    let cell = spacing / std::f32::consts::SQRT_2;
+    let cells = (size / cell).ceil() as usize;
+    let mut grid: Vec<Option<usize>> = vec![None; cells * cells];
+    let cell_of = |p: [f32; 2]| ((p[0] / cell) as usize).min(cells - 1) + ((p[1] / cell) as usize).min(cells - 1) * cells;
+
+    let first = [rng.gen_range(0.0 .. size), rng.gen_range(0.0 .. size)];
+    let mut points = vec![first];
+    let mut active = vec![0];
+    grid[cell_of(first)] = Some(0);
+
+    while !active.is_empty() {
+        let slot = rng.gen_range(0 .. active.len());
+        let center = points[active[slot]];
+        let mut found = false;
+
+        for _ in 0 .. ATTEMPTS {
+            // Uniform in the ring from spacing to twice the spacing
+            let angle = rng.gen_range(0.0 .. std::f32::consts::TAU);
+            let distance = spacing * (1.0 + 3.0 * rng.gen::<f32>()).sqrt();
+            let candidate = [center[0] + distance * angle.cos(), center[1] + distance * angle.sin()];
+            if candidate[0] < 0.0 || candidate[0] >= size || candidate[1] < 0.0 || candidate[1] >= size {
+                continue;
+            }
+
+            let (cx, cy) = ((candidate[0] / cell) as i64, (candidate[1] / cell) as i64);
+            let near = (cy - 2 ..= cy + 2).any(|ny| (cx - 2 ..= cx + 2).any(|nx| {
+                if nx < 0 || ny < 0 || nx >= cells as i64 || ny >= cells as i64 {
+                    return false;
+                }
+                grid[ny as usize * cells + nx as usize].is_some_and(|i| {
+                    let p = points[i];
+                    (p[0] - candidate[0]).hypot(p[1] - candidate[1]) < spacing
+                })
+            }));
+
+            if !near {
+                grid[cell_of(candidate)] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(slot);
+        }
+    }
+
+    points
+}
@@ -0,0 +1,209 @@
+use crate::height_grid::HeightGrid;
+use crate::height_source::{HeightSource, SharedHeightSource};
+use crate::perlin_noise::grid_coords;
+
+// Terrain Shaping
+// Turns noise that fills the whole plane into land surrounded by sea: masks between 0.0 (sea) and 1.0 (land)
+// are multiplied together and pull the detail noise down to the ocean floor where they are low
+// Radial, square and edge masks are placed in a region, the continent mask is large-scale noise and covers the whole plane
+// The heights can be offset so a chosen share of the region ends up above the sea level
+/* Example:
+let mut island = ShapedTerrain::new(detail, [0.0, 0.0], [25.0, 25.0]);
+island.add_mask(Mask::Radial { radius: 0.5, falloff: 0.5 });
+island.add_mask(Mask::Continent { noise: continent_noise, size: 10.0, threshold: 0.0, blend: 0.1 });
+island.fit_land_ratio(0.4, 0.0);
+
+let z = island.height(12.5, 12.5);
+*/
+
+#[derive(Clone)]
+pub enum Mask {
+    // Land within radius of the centre of the region, sea past radius + falloff, both as fractions of half the region
+    Radial { radius: f32, falloff: f32 },
+    // The same with a square outline
+    Square { radius: f32, falloff: f32 },
+    // Sea at the edge of the region, land from distance world units inside, for regions of any shape
+    EdgeDistance { distance: f32 },
+    // Noise with features about size world units across, land where it is above threshold (blended over threshold +- blend)
+    Continent { noise: SharedHeightSource, size: f32, threshold: f32, blend: f32 }
+}
+
+#[derive(Clone)]
+pub struct ShapedTerrain {
+    detail: SharedHeightSource,
+    region_min: [f32; 2],
+    region_max: [f32; 2],
+    masks: Vec<Mask>,
+    ocean_floor: f32,
+    offset: f32
+}
+
+impl ShapedTerrain {
+    // The region is the area the masks are placed in, from region_min to region_max in world units
+    pub fn new(detail: SharedHeightSource, region_min: [f32; 2], region_max: [f32; 2]) -> Self {
+        ShapedTerrain {
+            detail,
+            region_min,
+            region_max,
+            masks: Vec::new(),
+            ocean_floor: -0.6,
+            offset: 0.0
+        }
+    }
+
+    pub fn add_mask(&mut self, mask: Mask) {
+        self.masks.push(mask);
+    }
+
+    // Height where all masks are 0.0
+    pub fn set_ocean_floor(&mut self, ocean_floor: f32) {
+        self.ocean_floor = ocean_floor;
+    }
+
+    // Added to the detail heights before masking, set by fit_land_ratio
+    pub fn set_offset(&mut self, offset: f32) {
+        self.offset = offset;
+    }
+
+    pub fn get_offset(&self) -> f32 {
+        self.offset
+    }
+
+    // Share of the region above the sea level, measured on a grid of 256 x 256 samples
+    pub fn land_ratio(&self, sea_level: f32) -> f32 {
+        let (detail, mask) = self.sample_region();
+        land_ratio(&detail, &mask, self.ocean_floor, self.offset, sea_level)
+    }
+
+    // Finds the offset that puts the target share of the region above the sea level, returns the share reached
+    // Cells where the masks are low stay sea, so the target can be out of reach for masks that leave little land
+    pub fn fit_land_ratio(&mut self, target: f32, sea_level: f32) -> f32 {
+        let (detail, mask) = self.sample_region();
+
+        // More offset never gives less land, so bisect it
+        // Limited to about the range of the noise, past that the masks only grow cliffs instead of land
+        let (mut low, mut high) = (-1.0, 1.0);
+        for _ in 0 .. 32 {
+            let middle = (low + high) / 2.0;
+            if land_ratio(&detail, &mask, self.ocean_floor, middle, sea_level) < target {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        self.offset = high;
+        land_ratio(&detail, &mask, self.ocean_floor, self.offset, sea_level)
+    }
+
+    fn sample_region(&self) -> (HeightGrid, Vec<f32>) {
+        const SAMPLES: usize = 256;
+        let step_x = (self.region_max[0] - self.region_min[0]) / (SAMPLES - 1) as f32;
+        let step_y = (self.region_max[1] - self.region_min[1]) / (SAMPLES - 1) as f32;
+        let xs = grid_coords(self.region_min[0], step_x, SAMPLES);
+        let ys = grid_coords(self.region_min[1], step_y, SAMPLES);
+
+        let mut detail = HeightGrid::new(SAMPLES, SAMPLES);
+        self.detail.fill_grid(&xs, &ys, detail.data_mut());
+        let mut mask = vec![1.0; SAMPLES * SAMPLES];
+        self.fill_mask(&xs, &ys, &mut mask);
+
+        (detail, mask)
+    }
+
+    // Product of all masks for every grid point
+    fn fill_mask(&self, xs: &[f32], ys: &[f32], out: &mut [f32]) {
+        out.iter_mut().for_each(|m| *m = 1.0);
+        let mut noise = Vec::new();
+
+        for mask in self.masks.iter() {
+            match mask {
+                Mask::Continent { noise: source, size, threshold, blend } => {
+                    let noise_xs: Vec<f32> = xs.iter().map(|x| x / size).collect();
+                    let noise_ys: Vec<f32> = ys.iter().map(|y| y / size).collect();
+                    noise.resize(out.len(), 0.0);
+                    source.fill_grid(&noise_xs, &noise_ys, &mut noise);
+
+                    for (m, n) in out.iter_mut().zip(noise.iter()) {
+                        *m *= smoothstep(threshold - blend, threshold + blend, *n);
+                    }
+                },
+                _ => {
+                    for (row, &y) in ys.iter().enumerate() {
+                        for (col, &x) in xs.iter().enumerate() {
+                            out[row * xs.len() + col] *= self.region_mask(mask, x, y);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn mask(&self, x: f32, y: f32) -> f32 {
+        self.masks.iter().map(|mask| match mask {
+            Mask::Continent { noise, size, threshold, blend } => smoothstep(threshold - blend, threshold + blend, noise.height(x / size, y / size)),
+            _ => self.region_mask(mask, x, y)
+        }).product()
+    }
+
+    // Masks placed in the region
+    fn region_mask(&self, mask: &Mask, x: f32, y: f32) -> f32 {
+        let half_w = ((self.region_max[0] - self.region_min[0]) / 2.0).max(f32::EPSILON);
+        let half_h = ((self.region_max[1] - self.region_min[1]) / 2.0).max(f32::EPSILON);
+        // 0.0 in the centre, 1.0 in the middle of the edges
+        let dx = (x - self.region_min[0] - half_w) / half_w;
+        let dy = (y - self.region_min[1] - half_h) / half_h;
+
+        match mask {
+            Mask::Radial { radius, falloff } => 1.0 - smoothstep(*radius, radius + falloff, (dx * dx + dy * dy).sqrt()),
+            Mask::Square { radius, falloff } => 1.0 - smoothstep(*radius, radius + falloff, dx.abs().max(dy.abs())),
+            Mask::EdgeDistance { distance } => {
+                let to_edge = (half_w - (dx * half_w).abs()).min(half_h - (dy * half_h).abs());
+                smoothstep(0.0, *distance, to_edge)
+            },
+            Mask::Continent { .. } => unreachable!("not placed in the region")
+        }
+    }
+}
+
+impl HeightSource for ShapedTerrain {
+    fn height(&self, x: f32, y: f32) -> f32 {
+        shape(self.detail.height(x, y), self.mask(x, y), self.ocean_floor, self.offset)
+    }
+
+    fn fill_grid(&self, xs: &[f32], ys: &[f32], out: &mut [f32]) {
+        self.detail.fill_grid(xs, ys, out);
+        let mut mask = vec![1.0; out.len()];
+        self.fill_mask(xs, ys, &mut mask);
+
+        for (h, m) in out.iter_mut().zip(mask) {
+            *h = shape(*h, m, self.ocean_floor, self.offset);
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.detail.seed()
+    }
+
+    fn is_valid_coord(&self, scale: f32, plain_h: i32, plain_w: i32, x: f32, y: f32) -> bool {
+        self.detail.is_valid_coord(scale, plain_h, plain_w, x, y)
+    }
+}
+
+fn shape(detail: f32, mask: f32, ocean_floor: f32, offset: f32) -> f32 {
+    ocean_floor + (detail + offset - ocean_floor) * mask
+}
+
+fn land_ratio(detail: &HeightGrid, mask: &[f32], ocean_floor: f32, offset: f32, sea_level: f32) -> f32 {
+    let land = detail.data().iter().zip(mask).filter(|(h, m)| shape(**h, **m, ocean_floor, offset) > sea_level).count();
+    land as f32 / mask.len() as f32
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge1 <= edge0 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -1,9 +1,36 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Landscape,
+    FirstPerson
+}
+
+impl ViewMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            ViewMode::Landscape => ViewMode::FirstPerson,
+            ViewMode::FirstPerson => ViewMode::Landscape
+        }
+    }
+}
+
 pub struct Player {
     pub x: f32,
     pub y: f32,
     pub direction: f32,
-    pub speed: f32,
-    pub has_moved: bool
+    pub speed: f32, // Units per second
+    pub rotation_speed: f32, // Radians per second
+    pub has_moved: bool,
+    pub mode: ViewMode,
+    // State at the start of the current fixed step, used to interpolate rendering
+    prev_x: f32,
+    prev_y: f32,
+    prev_direction: f32
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Player::new()
+    }
 }
 
 impl Player {
@@ -13,7 +40,55 @@ impl Player {
             y: 0.0,
             direction: 0.0,
             speed: 0.1,
-            has_moved: false
+            rotation_speed: 0.5,
+            has_moved: false,
+            mode: ViewMode::Landscape,
+            prev_x: 0.0,
+            prev_y: 0.0,
+            prev_direction: 0.0
         }
     }
+
+    // Place the player without interpolating from the old position
+    pub fn teleport(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.prev_x = x;
+        self.prev_y = y;
+    }
+
+    // Remember the current state before running a fixed step
+    pub fn begin_step(&mut self) {
+        self.prev_x = self.x;
+        self.prev_y = self.y;
+        self.prev_direction = self.direction;
+    }
+
+    // Turn is in -1.0 .. 1.0, positive is anti-clockwise
+    pub fn rotate(&mut self, turn: f32, dt: f32) {
+        self.direction += turn * self.rotation_speed * dt;
+    }
+
+    // Direct rotation that is not part of the simulation (mouse look), applied without interpolation
+    pub fn look(&mut self, angle: f32) {
+        self.direction += angle;
+        self.prev_direction += angle;
+    }
+
+    // Movement relative to the player's direction over dt seconds, forward and right are in -1.0 .. 1.0
+    pub fn movement_delta(&self, forward: f32, right: f32, dt: f32) -> (f32, f32) {
+        let (sin, cos) = self.direction.sin_cos();
+        let dx = self.speed * dt * (forward * sin + right * cos);
+        let dy = self.speed * dt * (forward * cos - right * sin);
+        (dx, dy)
+    }
+
+    // Position and direction between the previous and current fixed step (alpha 0.0 .. 1.0)
+    pub fn interpolated(&self, alpha: f32) -> (f32, f32, f32) {
+        (
+            self.prev_x + (self.x - self.prev_x) * alpha,
+            self.prev_y + (self.y - self.prev_y) * alpha,
+            self.prev_direction + (self.direction - self.prev_direction) * alpha
+        )
+    }
 }
@@ -0,0 +1,51 @@
+// Fixed timestep
+// Turns variable frame times into a whole number of fixed size simulation steps,
+// so movement is the same at 30, 60 or 144 FPS
+/* Example:
+let mut timestep = FixedTimestep::new(120.0);
+
+loop {
+    for _ in 0 .. timestep.advance(frame_time) {
+        // update game state by timestep.get_step() seconds
+    }
+    let alpha = timestep.get_alpha(); // interpolate between the previous and current state when rendering
+}
+*/
+
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: f64,
+    accumulator: f64,
+    max_frame_time: f64
+}
+
+impl FixedTimestep {
+    pub fn new(updates_per_second: f64) -> Self {
+        FixedTimestep {
+            step: 1.0 / updates_per_second,
+            accumulator: 0.0,
+            // Long frames (window dragged, breakpoint hit) are capped so the simulation does not try to catch up forever
+            max_frame_time: 0.25
+        }
+    }
+
+    // Add the time of the last frame and return how many fixed steps to run
+    pub fn advance(&mut self, frame_time: f64) -> u32 {
+        self.accumulator += frame_time.clamp(0.0, self.max_frame_time);
+
+        let steps = (self.accumulator / self.step).floor();
+        self.accumulator -= steps * self.step;
+
+        steps as u32
+    }
+
+    // Length of one step in seconds
+    pub fn get_step(&self) -> f32 {
+        self.step as f32
+    }
+
+    // How far the leftover time is into the next step (0.0 .. 1.0), used to interpolate rendering
+    pub fn get_alpha(&self) -> f32 {
+        (self.accumulator / self.step) as f32
+    }
+}
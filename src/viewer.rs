@@ -0,0 +1,600 @@
+use std::{collections::{BTreeMap, HashMap}, f32::consts::PI, sync::Arc};
+use cgmath::{Matrix4, Rad, Vector3};
+use glfw::MouseButton;
+
+use terrain_generator::graphics::*;
+use terrain_generator::height_source::SharedHeightSource;
+use terrain_generator::cli::{self, CliOptions};
+use terrain_generator::config::{BiomesConfig, ColorStop, Config, ConfigWatcher, WaterConfig, MAX_COLOR_STOPS};
+use terrain_generator::chunks::{Chunk, ChunkKey, ChunkManager, LodSettings};
+use terrain_generator::structs::{Player, ViewMode};
+use terrain_generator::water::Water;
+use terrain_generator::hydrology::RiverNetwork;
+use terrain_generator::scatter::Instance;
+use terrain_generator::culling::{Aabb, CullStats, Frustum};
+use mesh::Mesh;
+use instance_buffer::InstanceBuffer;
+use input::{InputAction, InputBindings};
+
+// Terrain is streamed in chunks around the view, so moving only generates the chunks coming into range
+// Distant chunks use coarser grids (LOD), chosen by their error in pixels on the window
+fn create_chunk_manager(config: &Config, terrain: &SharedHeightSource) -> ChunkManager {
+    let chunk_size = 16;
+    // Chunks are generated on worker threads, at most a few are uploaded to the GPU per frame to avoid stalls
+    let worker_count = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2).saturating_sub(1).max(1);
+    let mut chunk_manager = ChunkManager::new(chunk_size, config.terrain.scale, Arc::clone(terrain), worker_count);
+    chunk_manager.set_lod(LodSettings { screen_factor: config.window.height as f32 / (2.0 * (30.0f32).to_radians().tan()), ..LodSettings::default() });
+    chunk_manager.set_max_uploads_per_frame(4);
+    chunk_manager.set_biomes(cli::build_biome_map(config, terrain).map(Arc::new));
+    chunk_manager.set_scatter(cli::build_scatter(config, terrain).map(Arc::new));
+    chunk_manager
+}
+
+// The model of a [[scatter]] layer, bounds in model space (y up)
+struct ScatterModel {
+    mesh: Mesh,
+    color: [f32; 3],
+    bounds: Aabb
+}
+
+fn load_scatter_models(config: &Config) -> Result<Vec<ScatterModel>, String> {
+    config.scatter.iter().enumerate().map(|(i, layer)| {
+        let path = layer.model.to_str().filter(|_| layer.model.is_file())
+            .ok_or_else(|| format!("scatter[{}].model: {} not found", i, layer.model.display()))?;
+        let object = object_loader::ObjectLoader::new(path);
+        Ok(ScatterModel {
+            mesh: Mesh::new(object.get_vertices(), &object.get_indices()),
+            color: layer.color,
+            bounds: object.get_bounds()
+        })
+    }).collect()
+}
+
+// The view scales the plain to 2 units across but keeps heights, the model heights are scaled the same way
+fn object_height_scale(plain_extent_x: f32, plain_extent_y: f32) -> f32 {
+    2.0 / plain_extent_x.max(plain_extent_y)
+}
+
+// All objects of one layer on a chunk, drawn with one instanced draw unless the batch is off screen
+struct InstanceBatch {
+    layer: usize,
+    instances: InstanceBuffer, // xyz relative to the chunk origin and scale (vec4), rotation (float)
+    bounds: Aabb // Relative to the chunk origin
+}
+
+fn create_instance_batches(chunk: &Chunk, models: &[ScatterModel], height_scale: f32) -> Vec<InstanceBatch> {
+    let mut by_layer: BTreeMap<usize, Vec<&Instance>> = BTreeMap::new();
+    for instance in chunk.instances.iter() {
+        by_layer.entry(instance.layer).or_default().push(instance);
+    }
+
+    by_layer.into_iter().filter_map(|(layer, instances)| {
+        let model = models.get(layer)?.bounds;
+        // Rotated around z, so the model reaches its largest horizontal distance from the axis in every direction
+        let radius = model.min[0].abs().max(model.max[0].abs()).hypot(model.min[2].abs().max(model.max[2].abs()));
+
+        let mut data = Vec::with_capacity(instances.len() * 5);
+        let mut bounds = Aabb::empty();
+        for instance in instances {
+            let [x, y, z] = instance.position;
+            data.extend_from_slice(&[x, y, z, instance.scale, instance.rotation]);
+
+            let (reach, height) = (radius * instance.scale, height_scale * instance.scale);
+            bounds.add_point([x - reach, y - reach, z + model.min[1] * height]);
+            bounds.add_point([x + reach, y + reach, z + model.max[1] * height]);
+        }
+
+        Some(InstanceBatch { layer, instances: InstanceBuffer::new(&data, &[4, 1]), bounds })
+    }).collect()
+}
+
+// Chunks classified into biomes carry primary id, secondary id and blend per vertex at location 1
+fn create_terrain_mesh(chunk: &Chunk) -> Mesh {
+    if chunk.biomes.is_empty() {
+        return Mesh::new(&chunk.vertices, &chunk.indices);
+    }
+
+    let vertices: Vec<f32> = chunk.vertices.chunks_exact(3).zip(chunk.biomes.chunks_exact(3))
+        .flat_map(|(position, biome)| position.iter().chain(biome).copied().collect::<Vec<f32>>())
+        .collect();
+    Mesh::with_layout(&vertices, &chunk.indices, &[3, 3])
+}
+
+// Biome colours for the terrain shader, without a [biomes] section the ramp colours everything
+fn set_biome_uniforms(shader: &shader_reader::ShaderReader, biomes: Option<&BiomesConfig>) {
+    shader.set_1i_uniform("useBiomes", biomes.is_some() as i32);
+    if let Some(biomes) = biomes {
+        shader.set_3fv_uniform("biomeColors", &biomes.colors());
+    }
+}
+
+// Upload the colour ramp to the terrain shader, unused stops are left as they are
+fn set_color_ramp(shader: &shader_reader::ShaderReader, ramp: &[ColorStop]) {
+    let ramp = &ramp[.. ramp.len().min(MAX_COLOR_STOPS)];
+    let heights: Vec<f32> = ramp.iter().map(|stop| stop.height).collect();
+    let colors: Vec<[f32; 3]> = ramp.iter().map(|stop| stop.color).collect();
+
+    shader.bind();
+    shader.set_1i_uniform("rampSize", ramp.len() as i32);
+    shader.set_1fv_uniform("rampHeights", &heights);
+    shader.set_3fv_uniform("rampColors", &colors);
+}
+
+fn water_from_config(config: &Config) -> Water {
+    Water { sea_level: config.terrain.sea_level, impassable_depth: config.water.impassable_depth }
+}
+
+fn set_water_uniforms(shader: &shader_reader::ShaderReader, water: &WaterConfig) {
+    shader.bind();
+    shader.set_3fv_uniform("shallowColor", &[water.shallow_color]);
+    shader.set_3fv_uniform("deepColor", &[water.deep_color]);
+    shader.set_1f_uniform("deepDepth", water.deep_depth);
+    shader.set_1f_uniform("opacity", water.opacity);
+    shader.set_1f_uniform("foamWidth", water.foam_width);
+}
+
+// Water surface over the parts of a chunk below the sea level, None when the chunk is dry
+// The chunk vertices flattened to the sea level, with the depth of the ground below each one
+fn create_water_mesh(config: &Config, vertices: &[f32], indices: &[i32]) -> Option<Mesh> {
+    if !config.water.enabled {
+        return None;
+    }
+
+    let surface = water_from_config(config).surface_indices(vertices, indices, config.water.foam_width);
+    if surface.is_empty() {
+        return None;
+    }
+
+    let sea_level = config.terrain.sea_level;
+    let water_vertices: Vec<f32> = vertices.chunks_exact(3).flat_map(|v| [v[0], v[1], sea_level, sea_level - v[2]]).collect();
+    Some(Mesh::with_layout(&water_vertices, &surface, &[3, 1]))
+}
+
+// Rivers and lakes on the plain, drawn with the water shader, bounds in world coordinates
+fn create_river_mesh(rivers: Option<&RiverNetwork>) -> Option<(Mesh, Aabb)> {
+    let (vertices, indices) = rivers?.water_mesh();
+    let positions: Vec<f32> = vertices.chunks_exact(4).flat_map(|v| [v[0], v[1], v[2]]).collect();
+    (!indices.is_empty()).then(|| (Mesh::with_layout(&vertices, &indices, &[3, 1]), Aabb::from_points(&positions)))
+}
+
+// Drawn and culled meshes of the last frame
+#[derive(Default)]
+struct FrameStats {
+    terrain: CullStats,
+    water: CullStats,
+    objects: CullStats
+}
+
+impl FrameStats {
+    fn reset(&mut self) {
+        self.terrain.reset();
+        self.water.reset();
+        self.objects.reset();
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "chunks {}/{}, water {}/{}, object batches {}/{} drawn",
+            self.terrain.drawn, self.terrain.total(), self.water.drawn, self.water.total(), self.objects.drawn, self.objects.total()
+        )
+    }
+}
+
+// Interactive viewer
+// Terrain heights come from the noise map (hashed gradients unless a map size asks for a bounded map) or a heightmap image
+pub fn run_viewer(options: &CliOptions) -> Result<(), String> {
+    let mut config = options.config.clone();
+    let cli::Terrain { source: mut terrain, rivers } = cli::build_terrain(&config)?;
+    if let Some(seed) = terrain.seed() {
+        println!("Seed: {}", seed);
+    }
+
+    // With live_reload the config file is checked once a second and changes are applied while running
+    let mut config_watcher = options.get_config_path().map(ConfigWatcher::new);
+    let mut reload_timer = 0.0;
+    // The culling counts are shown in the window title, refreshed every second
+    let mut frame_stats = FrameStats::default();
+    let mut stats_timer = 0.0;
+
+    //Initialize player
+    let mut player = Player::new();
+    player.teleport(config.terrain.origin[0], config.terrain.origin[1]);
+    // Same as the old per frame values (0.01 and PI/500) at 60 FPS
+    player.speed = 0.6; // Units per second
+    player.rotation_speed = 60.0 * PI/500.0; // Radians per second
+
+    // Initialize map
+    let [mut plain_w, mut plain_h] = config.terrain.plain;
+    let mut scale = config.terrain.scale;
+
+    // World size of the plain, the plain is mapped to -1.0 .. 1.0 on screen like a single mesh would be
+    let mut plain_extent_x = (plain_w - 1) as f32 * scale;
+    let mut plain_extent_y = (plain_h - 1) as f32 * scale;
+
+    let mut chunk_manager = create_chunk_manager(&config, &terrain);
+    let mut water = water_from_config(&config);
+
+    // Load spaceship (3D object)
+    /*
+    let obj_loader = object_loader::ObjectLoader::new("resources/spaceship/placeholder_spaceship.gltf");
+    let spaceship_vertices = obj_loader.get_vertices();
+    let spaceship_indices = obj_loader.get_indices();
+    let spaceship_triangle_count = obj_loader.get_triangle_count();
+
+    println!("{:?}", spaceship_vertices);
+    */
+
+    // Initialize application
+    let mut window = window::Window::new(config.window.width, config.window.height, "Terrain Generator");
+    window.init_gl();
+    window.set_fps(config.window.swap_interval);
+    window.set_fixed_update_rate(config.window.update_rate);
+    window.set_bindings(config.apply_controls(InputBindings::load("resources/controls.cfg"))?);
+    for (id, name) in window.get_gamepads() {
+        println!("Gamepad {:?}: {}", id, name);
+    }
+
+    // Setup terrain rendering, one GPU mesh per loaded chunk
+    let mut terrain_meshes: HashMap<ChunkKey, Mesh> = HashMap::new();
+    // Water surfaces of the chunks that reach below the sea level
+    let mut water_meshes: HashMap<ChunkKey, Mesh> = HashMap::new();
+    // Scattered objects per chunk, drawn instanced with the model of their layer
+    let mut instance_batches: HashMap<ChunkKey, Vec<InstanceBatch>> = HashMap::new();
+    let mut scatter_models = load_scatter_models(&config)?;
+
+    // Load shaders for terrain
+    let mut terrain_shader = shader_reader::ShaderReader::new("resources/terrain/vertex_shader.glsl", "resources/terrain/fragment_shader.glsl");
+    terrain_shader.bind();
+
+    // Setup spaceship rendering
+    /*
+    let spaceship_vao = vao::ArrayObject::new();
+    spaceship_vao.bind();
+
+    let spaceship_vbo = vbo::BufferObject::new(gl::ARRAY_BUFFER, gl::STATIC_DRAW);
+    spaceship_vbo.bind();
+    spaceship_vbo.store_f32_data(spaceship_vertices);
+
+    let spaceship_ibo = vbo::BufferObject::new(gl::ELEMENT_ARRAY_BUFFER, gl::STATIC_DRAW);
+    spaceship_ibo.bind();
+    spaceship_ibo.store_i32_data(&spaceship_indices);
+
+    let spaceship_position_attribute = v_attribute::VertexAttribute::new(0, 3, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<GLfloat>() as GLsizei, ptr::null());
+    spaceship_position_attribute.enable();
+
+    // Load shaders for spaceship
+    let mut spaceship_shader = shader_reader::ShaderReader::new("resources/spaceship/vertex_shader.glsl", "resources/spaceship/fragment_shader.glsl");
+    spaceship_shader.bind();
+    */
+
+    // Landscape camera: tilt and zoom are changed with the mouse (drag to orbit, scroll to zoom)
+    let mut camera_tilt = PI/3.0;
+    let mut camera_zoom = 0.75;
+    let orbit_sensitivity = 0.005;
+    let look_sensitivity = 0.002;
+    let zoom_step: f32 = 1.1;
+
+    // Create a transformation matrix and apply it to the shader
+    let mut terrain_transform = Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)) * Matrix4::from_angle_x(Rad(camera_tilt)) * Matrix4::from_scale(camera_zoom);
+    terrain_shader.create_uniform("transform");
+    terrain_shader.set_matrix4fv_uniform("transform", &terrain_transform);
+
+    for uniform in ["rampSize", "rampHeights", "rampColors"] {
+        terrain_shader.create_uniform(uniform);
+    }
+    set_color_ramp(&terrain_shader, &config.color_ramp);
+    for uniform in ["useBiomes", "biomeColors"] {
+        terrain_shader.create_uniform(uniform);
+    }
+    set_biome_uniforms(&terrain_shader, config.biomes.as_ref());
+
+    // Load shaders for water, drawn transparent over the terrain
+    let mut water_shader = shader_reader::ShaderReader::new("resources/water/vertex_shader.glsl", "resources/water/fragment_shader.glsl");
+    for uniform in ["transform", "shallowColor", "deepColor", "deepDepth", "opacity", "foamWidth"] {
+        water_shader.create_uniform(uniform);
+    }
+    set_water_uniforms(&water_shader, &config.water);
+    let mut river_mesh = create_river_mesh(rivers.as_ref());
+
+    // Load shaders for scattered objects
+    let mut scatter_shader = shader_reader::ShaderReader::new("resources/scatter/vertex_shader.glsl", "resources/scatter/fragment_shader.glsl");
+    for uniform in ["transform", "heightScale", "color"] {
+        scatter_shader.create_uniform(uniform);
+    }
+
+    /*
+    let mut spaceship_transform = Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)) * Matrix4::from_scale(0.25);
+    spaceship_shader.create_uniform("transform");
+    spaceship_shader.set_matrix4fv_uniform("transform", &spaceship_transform);
+    */
+
+    // Setup Z-buffer (depth) testing
+    unsafe {
+        gl::Enable(gl::DEPTH_TEST);
+    }
+
+    while !window.close() {
+        player.has_moved = false;
+
+        // Live reload, an invalid file keeps the current settings
+        reload_timer += window.get_delta_time();
+        if config.live_reload && reload_timer >= 1.0 {
+            reload_timer = 0.0;
+
+            if config_watcher.as_mut().is_some_and(|watcher| watcher.changed()) {
+                match options.load_config() {
+                    Ok(mut new_config) => {
+                        if new_config.scatter != config.scatter {
+                            match load_scatter_models(&new_config) {
+                                Ok(models) => scatter_models = models,
+                                Err(e) => {
+                                    println!("Objects not reloaded: {}", e);
+                                    new_config.scatter = config.scatter.clone();
+                                }
+                            }
+                        }
+                        if new_config.noise != config.noise || new_config.terrain != config.terrain
+                            || new_config.shape != config.shape || new_config.filters != config.filters || new_config.rivers != config.rivers {
+                            match cli::build_terrain(&new_config) {
+                                Ok(new_terrain) => {
+                                    terrain = new_terrain.source;
+                                    river_mesh = create_river_mesh(new_terrain.rivers.as_ref());
+                                    [plain_w, plain_h] = new_config.terrain.plain;
+                                    scale = new_config.terrain.scale;
+                                    plain_extent_x = (plain_w - 1) as f32 * scale;
+                                    plain_extent_y = (plain_h - 1) as f32 * scale;
+
+                                    // The old manager joins its workers when dropped
+                                    chunk_manager = create_chunk_manager(&new_config, &terrain);
+                                    terrain_meshes.clear();
+                                    water_meshes.clear();
+                                    instance_batches.clear();
+                                },
+                                Err(e) => println!("Config not reloaded: {}", e)
+                            }
+                        } else if new_config.biomes != config.biomes || new_config.scatter != config.scatter {
+                            // Same terrain, only the chunks are classified and scattered again
+                            let mut cleared = chunk_manager.set_biomes(cli::build_biome_map(&new_config, &terrain).map(Arc::new));
+                            cleared.extend(chunk_manager.set_scatter(cli::build_scatter(&new_config, &terrain).map(Arc::new)));
+                            for key in cleared {
+                                terrain_meshes.remove(&key);
+                                water_meshes.remove(&key);
+                                instance_batches.remove(&key);
+                            }
+                        }
+                        if new_config.biomes != config.biomes {
+                            terrain_shader.bind();
+                            set_biome_uniforms(&terrain_shader, new_config.biomes.as_ref());
+                        }
+                        if new_config.water != config.water || new_config.terrain.sea_level != config.terrain.sea_level {
+                            set_water_uniforms(&water_shader, &new_config.water);
+                            water = water_from_config(&new_config);
+
+                            // The surfaces cover different triangles at another sea level
+                            water_meshes.clear();
+                            for key in terrain_meshes.keys() {
+                                if let Some(mesh) = chunk_manager.get(*key).and_then(|chunk| create_water_mesh(&new_config, &chunk.vertices, &chunk.indices)) {
+                                    water_meshes.insert(*key, mesh);
+                                }
+                            }
+                        }
+                        if new_config.color_ramp != config.color_ramp {
+                            set_color_ramp(&terrain_shader, &new_config.color_ramp);
+                        }
+                        if new_config.window != config.window {
+                            window.set_fps(new_config.window.swap_interval);
+                            window.set_fixed_update_rate(new_config.window.update_rate);
+                            if (new_config.window.width, new_config.window.height) != (config.window.width, config.window.height) {
+                                println!("The window size is applied on the next start");
+                            }
+                        }
+                        if new_config.controls != config.controls {
+                            // Validated while loading
+                            window.set_bindings(new_config.apply_controls(InputBindings::load("resources/controls.cfg"))?);
+                        }
+
+                        println!("Reloaded {}", config_watcher.as_ref().map(|watcher| watcher.get_path().display().to_string()).unwrap_or_default());
+                        config = new_config;
+                    },
+                    Err(e) => println!("Config not reloaded: {}", e)
+                }
+            }
+        }
+
+        // Switch between landscape and first person view, first person captures the cursor for mouse look
+        if window.is_action_just_pressed(InputAction::ToggleMode) {
+            player.mode = player.mode.toggle();
+            window.set_cursor_captured(player.mode == ViewMode::FirstPerson);
+            println!("{:?}", player.mode);
+        }
+
+        match player.mode {
+            ViewMode::Landscape => {
+                // Left mouse drag orbits the camera: horizontal rotates like QE, vertical changes the tilt
+                if window.is_mouse_button_pressed(MouseButton::Button1) {
+                    let (drag_x, drag_y) = window.get_cursor_delta();
+                    player.look(drag_x as f32 * orbit_sensitivity);
+                    camera_tilt = (camera_tilt - drag_y as f32 * orbit_sensitivity).clamp(0.0, PI/2.0);
+                }
+
+                // Scroll to zoom
+                let (_, scroll_y) = window.get_scroll_delta();
+                if scroll_y != 0.0 {
+                    camera_zoom = (camera_zoom * zoom_step.powf(scroll_y as f32)).clamp(0.05, 3.0);
+                }
+            },
+            ViewMode::FirstPerson => {
+                // Mouse look
+                let (look_x, _) = window.get_cursor_delta();
+                player.look(look_x as f32 * look_sensitivity);
+            }
+        }
+
+        // Simulate in fixed steps so speed does not depend on the frame rate
+        let turn = window.get_rotation_axis();
+        let (forward, right) = window.get_movement_axes();
+        let dt = window.get_fixed_step();
+
+        for _ in 0 .. window.get_fixed_steps() {
+            player.begin_step();
+
+            // QE or right stick for rotation
+            if turn != 0.0 {
+                player.rotate(turn, dt);
+            }
+
+            // WASD or left stick movement relative to player direction
+            let (dx, dy) = player.movement_delta(forward, right, dt);
+
+            // Apply movement if within bounds
+            if dx != 0.0 || dy != 0.0 {
+                let new_x = player.x + dx;
+                let new_y = player.y + dy;
+
+                // Walking stops at deep water, the player stands in the middle of the plain
+                let walkable = player.mode != ViewMode::FirstPerson || water.is_passable(terrain.as_ref(), new_x + plain_extent_x * 0.5, new_y + plain_extent_y * 0.5);
+
+                if terrain.is_valid_coord(scale, plain_h, plain_w, new_x, new_y) && walkable {
+                    player.x = new_x;
+                    player.y = new_y;
+                    player.has_moved = true;
+                } else {
+                    println!("Edge");
+                }
+            }
+        }
+
+        if player.has_moved {
+            println!("x = {}, y = {}, direction = {}", player.x, player.y, player.direction);
+        }
+
+        // Render between the last two fixed steps
+        let (view_x, view_y, view_direction) = player.interpolated(window.get_interpolation_alpha());
+
+        terrain_transform = Matrix4::from_angle_x(Rad(camera_tilt)) * Matrix4::from_scale(camera_zoom) * Matrix4::from_angle_z(Rad(view_direction));
+        //spaceship_transform = Matrix4::from_scale(0.25) * Matrix4::from_angle_z(Rad(view_direction));
+
+        // The player is the corner of the plain, the view is centered on the middle of the plain
+        let center_x = view_x + plain_extent_x * 0.5;
+        let center_y = view_y + plain_extent_y * 0.5;
+        let world_to_plain = Matrix4::from_nonuniform_scale(2.0 / plain_extent_x, 2.0 / plain_extent_y, 1.0);
+
+        // Stream chunks in and out around the view, zooming out shows more of the world
+        // The plain half diagonal, grown by the zoom and doubled to cover the tilted view
+        let view_distance = plain_extent_x.hypot(plain_extent_y) / camera_zoom;
+        let changes = chunk_manager.update(center_x, center_y, view_distance);
+        for key in changes.evicted {
+            terrain_meshes.remove(&key);
+            water_meshes.remove(&key);
+            instance_batches.remove(&key);
+        }
+        for key in changes.loaded {
+            if let Some(chunk) = chunk_manager.get(key) {
+                terrain_meshes.insert(key, create_terrain_mesh(chunk));
+                if !chunk.instances.is_empty() {
+                    instance_batches.insert(key, create_instance_batches(chunk, &scatter_models, object_height_scale(plain_extent_x, plain_extent_y)));
+                }
+                if let Some(mesh) = create_water_mesh(&config, &chunk.vertices, &chunk.indices) {
+                    water_meshes.insert(key, mesh);
+                }
+            }
+        }
+
+        // Everything is tested in world coordinates against the frustum of the whole view transform, draws entirely off screen are skipped
+        let world_to_clip = terrain_transform * world_to_plain * Matrix4::from_translation(Vector3::new(-center_x, -center_y, 0.0));
+        let frustum = Frustum::from_matrix(&world_to_clip.into());
+        frame_stats.reset();
+
+        unsafe {
+            gl::ClearColor(0.25, 0.25, 0.25, 1.0); // Gray background color
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+            // Render terrain chunks, each placed relative to the view center
+            terrain_shader.bind();
+            for (key, mesh) in terrain_meshes.iter() {
+                if let Some(chunk) = chunk_manager.get(*key) {
+                    if !frame_stats.terrain.test(&frustum, &chunk.bounds.translated([chunk.origin_x, chunk.origin_y, 0.0])) {
+                        continue;
+                    }
+
+                    let offset = Vector3::new(chunk.origin_x - center_x, chunk.origin_y - center_y, 0.0);
+                    let chunk_transform = terrain_transform * world_to_plain * Matrix4::from_translation(offset);
+                    terrain_shader.set_matrix4fv_uniform("transform", &chunk_transform);
+                    mesh.draw();
+                }
+            }
+            terrain_shader.unbind();
+
+            // Render scattered objects, one instanced draw per layer and chunk, skipping the batches that are off screen
+            if !instance_batches.is_empty() {
+                scatter_shader.bind();
+                scatter_shader.set_1f_uniform("heightScale", object_height_scale(plain_extent_x, plain_extent_y));
+                for (key, batches) in instance_batches.iter() {
+                    if let Some(chunk) = chunk_manager.get(*key) {
+                        let origin = [chunk.origin_x, chunk.origin_y, 0.0];
+                        let offset = Vector3::new(chunk.origin_x - center_x, chunk.origin_y - center_y, 0.0);
+                        scatter_shader.set_matrix4fv_uniform("transform", &(terrain_transform * world_to_plain * Matrix4::from_translation(offset)));
+
+                        for batch in batches.iter().filter(|batch| frame_stats.objects.test(&frustum, &batch.bounds.translated(origin))) {
+                            if let Some(model) = scatter_models.get(batch.layer) {
+                                scatter_shader.set_3fv_uniform("color", &[model.color]);
+                                model.mesh.draw_instances(&batch.instances);
+                            }
+                        }
+                    }
+                }
+                scatter_shader.unbind();
+            }
+
+            // Render water after all terrain, blended and without depth writes so the ground below shows through
+            if !water_meshes.is_empty() || river_mesh.is_some() {
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                gl::DepthMask(gl::FALSE);
+
+                water_shader.bind();
+                for (key, mesh) in water_meshes.iter() {
+                    if let Some(chunk) = chunk_manager.get(*key) {
+                        // The surface spans the chunk at the sea level, which is above the lowest point of the chunk
+                        let mut bounds = chunk.bounds.translated([chunk.origin_x, chunk.origin_y, 0.0]);
+                        bounds.add_point([bounds.min[0], bounds.min[1], config.terrain.sea_level]);
+                        if !frame_stats.water.test(&frustum, &bounds) {
+                            continue;
+                        }
+
+                        let offset = Vector3::new(chunk.origin_x - center_x, chunk.origin_y - center_y, 0.0);
+                        water_shader.set_matrix4fv_uniform("transform", &(terrain_transform * world_to_plain * Matrix4::from_translation(offset)));
+                        mesh.draw();
+                    }
+                }
+                // River vertices are in world coordinates
+                if let Some((mesh, _)) = river_mesh.as_ref().filter(|(_, bounds)| frame_stats.water.test(&frustum, bounds)) {
+                    water_shader.set_matrix4fv_uniform("transform", &world_to_clip);
+                    mesh.draw();
+                }
+                water_shader.unbind();
+
+                gl::DepthMask(gl::TRUE);
+                gl::Disable(gl::BLEND);
+            }
+
+            // Render spaceship
+            /*
+            spaceship_vao.bind();
+            spaceship_shader.bind();
+            spaceship_shader.set_matrix4fv_uniform("transform", &spaceship_transform);
+            gl::DrawElements(gl::TRIANGLES, spaceship_triangle_count, gl::UNSIGNED_INT, ptr::null());
+            spaceship_shader.unbind();
+            */
+        }
+        stats_timer += window.get_delta_time();
+        if stats_timer >= 1.0 {
+            stats_timer = 0.0;
+            window.set_title(&format!("Terrain Generator - {}", frame_stats.summary()));
+        }
+
+        window.update();
+    }
+
+    Ok(())
+}
\ No newline at end of file
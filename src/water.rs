@@ -0,0 +1,44 @@
+use crate::height_source::HeightSource;
+
+// Water
+// The sea: a flat surface at the sea level covering the terrain below it
+// Gives the triangles of a terrain mesh the water surface has to cover and where the sea is too deep to walk through
+/* Example:
+let water = Water { sea_level: 0.0, impassable_depth: Some(0.1) };
+
+let surface = water.surface_indices(&chunk.vertices, &chunk.indices, 0.02);
+if water.is_passable(&terrain, x, y) {
+    // move
+}
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Water {
+    pub sea_level: f32,
+    pub impassable_depth: Option<f32> // Deeper water blocks walking, everything is passable when missing
+}
+
+impl Water {
+    // How far the ground is below the surface, negative on land
+    pub fn depth(&self, source: &(impl HeightSource + ?Sized), x: f32, y: f32) -> f32 {
+        self.sea_level - source.height(x, y)
+    }
+
+    pub fn is_passable(&self, source: &(impl HeightSource + ?Sized), x: f32, y: f32) -> bool {
+        match self.impassable_depth {
+            Some(max_depth) => self.depth(source, x, y) <= max_depth,
+            None => true
+        }
+    }
+
+    // Triangles of an xyz mesh with a vertex below the sea level (+ margin, to draw foam on the beach), the rest is dry land
+    // Drawn with the z of the terrain flattened to the sea level, the shader gets the depth from the original z
+    pub fn surface_indices(&self, vertices: &[f32], indices: &[i32], margin: f32) -> Vec<i32> {
+        let below = |i: i32| vertices[i as usize * 3 + 2] < self.sea_level + margin;
+        indices.chunks_exact(3)
+            .filter(|triangle| triangle.iter().any(|&i| below(i)))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
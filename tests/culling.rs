@@ -0,0 +1,85 @@
+use terrain_generator::culling::{Aabb, CullStats, Frustum};
+
+// Column-major like glOrtho: x in -10 .. 10, y in -5 .. 5, looking down -z from 1 to 100
+fn orthographic() -> Frustum {
+    let (near, far) = (1.0, 100.0);
+    Frustum::from_matrix(&[
+        [0.1, 0.0, 0.0, 0.0],
+        [0.0, 0.2, 0.0, 0.0],
+        [0.0, 0.0, -2.0 / (far - near), 0.0],
+        [0.0, 0.0, -(far + near) / (far - near), 1.0]
+    ])
+}
+
+// Column-major like gluPerspective with a 90 degree field of view and a square aspect: |x|, |y| <= -z from 1 to 100
+fn perspective() -> Frustum {
+    let (near, far) = (1.0, 100.0);
+    Frustum::from_matrix(&[
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, (far + near) / (near - far), -1.0],
+        [0.0, 0.0, 2.0 * far * near / (near - far), 0.0]
+    ])
+}
+
+#[test]
+fn orthographic_boxes() {
+    let frustum = orthographic();
+    assert!(frustum.contains_point([9.0, -4.0, -50.0]));
+    assert!(!frustum.contains_point([11.0, 0.0, -50.0]));
+
+    assert!(frustum.intersects(&Aabb::new([-1.0, -1.0, -10.0], [1.0, 1.0, -5.0])), "inside");
+    assert!(!frustum.intersects(&Aabb::new([20.0, 0.0, -10.0], [25.0, 1.0, -5.0])), "right of the view");
+    assert!(!frustum.intersects(&Aabb::new([-1.0, 6.0, -10.0], [1.0, 8.0, -5.0])), "above the view");
+    assert!(!frustum.intersects(&Aabb::new([-1.0, -1.0, 2.0], [1.0, 1.0, 5.0])), "behind the near plane");
+    assert!(!frustum.intersects(&Aabb::new([-1.0, -1.0, -200.0], [1.0, 1.0, -150.0])), "beyond the far plane");
+    assert!(frustum.intersects(&Aabb::new([5.0, -1.0, -10.0], [15.0, 1.0, -5.0])), "straddling the right plane");
+    assert!(frustum.intersects(&Aabb::new([-1.0, -1.0, -150.0], [1.0, 1.0, -50.0])), "straddling the far plane");
+    assert!(frustum.intersects(&Aabb::new([-50.0, -50.0, -500.0], [50.0, 50.0, 500.0])), "around the whole view");
+}
+
+#[test]
+fn perspective_boxes() {
+    let frustum = perspective();
+    assert!(frustum.contains_point([4.0, -4.0, -5.0]));
+    assert!(!frustum.contains_point([6.0, 0.0, -5.0]));
+
+    assert!(frustum.intersects(&Aabb::new([-1.0, -1.0, -10.0], [1.0, 1.0, -5.0])), "inside");
+    assert!(!frustum.intersects(&Aabb::new([8.0, 0.0, -5.0], [9.0, 1.0, -4.0])), "right of the view");
+    assert!(!frustum.intersects(&Aabb::new([-1.0, -1.0, 2.0], [1.0, 1.0, 3.0])), "behind the camera");
+    assert!(!frustum.intersects(&Aabb::new([-1.0, -1.0, -200.0], [1.0, 1.0, -150.0])), "beyond the far plane");
+    assert!(frustum.intersects(&Aabb::new([3.0, 0.0, -5.0], [7.0, 1.0, -4.0])), "straddling the right plane");
+    assert!(frustum.intersects(&Aabb::new([-1.0, -1.0, -2.0], [1.0, 1.0, 0.5])), "straddling the near plane");
+
+    // Off screen at this distance for the perspective view, but inside the orthographic one
+    let wide = Aabb::new([6.0, 0.0, -3.0], [8.0, 1.0, -2.0]);
+    assert!(!frustum.intersects(&wide));
+    assert!(orthographic().intersects(&wide));
+}
+
+#[test]
+fn empty_box_is_never_visible() {
+    assert!(!orthographic().intersects(&Aabb::empty()));
+    assert!(!perspective().intersects(&Aabb::empty()));
+}
+
+#[test]
+fn cull_stats_count_draws() {
+    let frustum = perspective();
+    let boxes = [
+        Aabb::new([-1.0, -1.0, -10.0], [1.0, 1.0, -5.0]),
+        Aabb::new([8.0, 0.0, -5.0], [9.0, 1.0, -4.0]),
+        Aabb::new([3.0, 0.0, -5.0], [7.0, 1.0, -4.0]),
+        Aabb::new([-1.0, -1.0, 2.0], [1.0, 1.0, 3.0]),
+        Aabb::empty()
+    ];
+
+    let mut stats = CullStats::default();
+    let visible: Vec<bool> = boxes.iter().map(|bounds| stats.test(&frustum, bounds)).collect();
+    assert_eq!(visible, [true, false, true, false, false]);
+    assert_eq!(stats, CullStats { drawn: 2, culled: 3 });
+    assert_eq!(stats.total(), boxes.len());
+
+    stats.reset();
+    assert_eq!(stats.total(), 0);
+}
@@ -0,0 +1,49 @@
+use terrain_generator::structs::Player;
+use terrain_generator::timestep::FixedTimestep;
+
+// Walks forward while turning for total seconds of frames at fps, returns the rendered position and direction
+// The frame times do not add up to whole steps exactly, the last step can still be in the accumulator with alpha near 1.0,
+// so the state is compared the way the viewer draws it, interpolated between the last two steps
+fn simulate(fps: f64, total: f64) -> (f32, f32, f32) {
+    let mut timestep = FixedTimestep::new(120.0);
+    let mut player = Player::new();
+
+    let frames = (total * fps).round() as u32;
+    for _ in 0 .. frames {
+        for _ in 0 .. timestep.advance(1.0 / fps) {
+            player.begin_step();
+            player.rotate(1.0, timestep.get_step());
+            let (dx, dy) = player.movement_delta(1.0, 0.5, timestep.get_step());
+            player.x += dx;
+            player.y += dy;
+        }
+    }
+
+    player.interpolated(timestep.get_alpha())
+}
+
+#[test]
+fn same_motion_at_every_frame_rate() {
+    let (x, y, direction) = simulate(60.0, 3.0);
+    // Rendering interpolates towards the latest step, so it is up to one step behind
+    let rotation_speed = Player::new().rotation_speed;
+    assert!((direction - 3.0 * rotation_speed).abs() <= rotation_speed / 120.0 + 1e-4, "turned to {} in 3 seconds", direction);
+
+    for fps in [30.0, 144.0] {
+        let (other_x, other_y, other_direction) = simulate(fps, 3.0);
+        assert!((other_x - x).abs() < 1e-5 && (other_y - y).abs() < 1e-5, "{} fps ended at ({}, {}) instead of ({}, {})", fps, other_x, other_y, x, y);
+        assert!((other_direction - direction).abs() < 1e-5, "{} fps ended facing {} instead of {}", fps, other_direction, direction);
+    }
+}
+
+#[test]
+fn long_frames_are_capped() {
+    let mut timestep = FixedTimestep::new(120.0);
+
+    // A 2 second hitch only counts as 0.25 seconds
+    assert_eq!(timestep.advance(2.0), 30);
+    assert!(timestep.get_alpha() < 1e-6);
+
+    // Negative frame times add nothing
+    assert_eq!(timestep.advance(-1.0), 0);
+}
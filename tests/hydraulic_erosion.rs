@@ -0,0 +1,22 @@
+use terrain_generator::erosion::HydraulicErosion;
+use terrain_generator::height_grid::HeightGrid;
+use terrain_generator::perlin_noise::PerlinMap;
+
+fn eroded(seed: u64) -> HeightGrid {
+    let mut perlin_map = PerlinMap::new();
+    perlin_map.set_hashed(42);
+    let mut grid = HeightGrid::sample(&perlin_map, 0.0, 0.0, 0.05, 64, 64);
+
+    grid.apply(&HydraulicErosion { seed, iterations: 2000, ..Default::default() });
+    grid
+}
+
+#[test]
+fn same_seed_gives_same_result() {
+    assert_eq!(eroded(7).data(), eroded(7).data());
+}
+
+#[test]
+fn different_seed_gives_different_result() {
+    assert_ne!(eroded(7).data(), eroded(8).data());
+}
@@ -0,0 +1,92 @@
+use terrain_generator::height_grid::HeightGrid;
+use terrain_generator::hydrology::{fill_depressions, flow_field, FlowMethod, Rivers};
+use terrain_generator::perlin_noise::PerlinMap;
+
+fn noise_grid() -> HeightGrid {
+    let mut perlin_map = PerlinMap::new();
+    perlin_map.set_hashed(42);
+    HeightGrid::sample(&perlin_map, 0.0, 0.0, 0.05, 64, 64)
+}
+
+#[test]
+fn pit_is_filled_to_its_spill_height() {
+    // A pit at 0.2 inside a ring at 0.8, the border is higher except for one lower outlet
+    let mut grid = HeightGrid::new(5, 5);
+    grid.data_mut().fill(1.0);
+    for y in 1 .. 4 {
+        for x in 1 .. 4 {
+            grid.set(x, y, 0.8);
+        }
+    }
+    grid.set(2, 2, 0.2);
+    grid.set(0, 2, 0.6);
+
+    let filled = fill_depressions(&grid, 0.0, None);
+    assert_eq!(filled.get(2, 2), 0.8);
+    for (i, (&before, &after)) in grid.data().iter().zip(filled.data()).enumerate() {
+        if i != 2 * 5 + 2 {
+            assert_eq!(before, after, "cell {} is not in the pit but changed", i);
+        }
+    }
+}
+
+#[test]
+fn d8_flows_down_a_tilted_plane() {
+    // Falls towards x = 0, every cell drains straight into its left neighbour
+    let (width, height) = (6, 4);
+    let data = (0 .. width * height).map(|i| (i % width) as f32 * 0.1).collect();
+    let grid = HeightGrid::from_data(width, height, data);
+
+    let flow = flow_field(&grid, FlowMethod::D8, None);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            if x == 0 {
+                assert!(flow.receivers(x, y).is_empty(), "({}, {}) is on the low edge and drains out of the grid", x, y);
+            } else {
+                assert_eq!(flow.receivers(x, y), &[(y * width + x - 1, 1.0)], "({}, {})", x, y);
+            }
+            assert_eq!(flow.accumulation(x, y), (width - x) as f32, "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn d_infinity_shares_sum_to_one() {
+    let drained = fill_depressions(&noise_grid(), 1e-5, None);
+    let flow = flow_field(&drained, FlowMethod::DInfinity, None);
+
+    let mut split = 0;
+    for y in 0 .. flow.height() {
+        for x in 0 .. flow.width() {
+            let receivers = flow.receivers(x, y);
+            if receivers.is_empty() {
+                continue;
+            }
+            let total: f32 = receivers.iter().map(|(_, share)| share).sum();
+            assert!((total - 1.0).abs() < 1e-5, "shares of ({}, {}) sum to {}", x, y, total);
+            assert!(receivers.iter().all(|(_, share)| *share > 0.0 && *share <= 1.0));
+            if receivers.len() > 1 {
+                split += 1;
+            }
+        }
+    }
+    assert!(split > 0, "no cell split its flow");
+}
+
+#[test]
+fn rivers_only_above_the_threshold() {
+    // No lakes and no sea, so every wet cell is a river cell
+    let rivers = Rivers { threshold: 30.0, sea_level: None, min_lake_depth: f32::INFINITY, ..Default::default() };
+    let network = rivers.generate(&mut noise_grid());
+
+    let mut river_cells = 0;
+    for y in 0 .. network.flow.height() {
+        for x in 0 .. network.flow.width() {
+            let river = network.flow.accumulation(x, y) >= rivers.threshold;
+            assert_eq!(network.water_mask.get(x, y) > 0.0, river, "({}, {}) drains {} cells", x, y, network.flow.accumulation(x, y));
+            river_cells += river as usize;
+        }
+    }
+    assert!(river_cells > 0 && river_cells < 64 * 64, "{} river cells", river_cells);
+    assert!(!network.polylines.is_empty());
+}
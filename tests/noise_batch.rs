@@ -0,0 +1,52 @@
+use terrain_generator::perlin_noise::{grid_coords, PerlinMap};
+
+fn scalar(p_map: &PerlinMap, xs: &[f32], ys: &[f32]) -> Vec<f32> {
+    ys.iter().flat_map(|&y| xs.iter().map(move |&x| p_map.noise(x, y))).collect()
+}
+
+fn batch(p_map: &PerlinMap, xs: &[f32], ys: &[f32]) -> Vec<f32> {
+    let mut out = vec![0.0; xs.len() * ys.len()];
+    p_map.noise_grid(xs, ys, &mut out);
+    out
+}
+
+fn hashed_map() -> PerlinMap {
+    let mut p_map = PerlinMap::new();
+    p_map.set_hashed(7);
+    p_map
+}
+
+#[test]
+fn matches_scalar_noise() {
+    let p_map = hashed_map();
+    let xs = grid_coords(0.5, 0.05, 200);
+    let ys = grid_coords(-3.25, 0.05, 150);
+    assert_eq!(batch(&p_map, &xs, &ys), scalar(&p_map, &xs, &ys));
+}
+
+#[test]
+fn matches_scalar_noise_with_large_spacing() {
+    // Every sample in a cell of its own, far apart, like stats with --scale 1000
+    let p_map = hashed_map();
+    let xs = grid_coords(-20000.3, 1000.0, 64);
+    let ys = grid_coords(12.7, 1000.0, 64);
+    assert_eq!(batch(&p_map, &xs, &ys), scalar(&p_map, &xs, &ys));
+}
+
+#[test]
+fn matches_scalar_noise_with_stored_gradients() {
+    let mut p_map = PerlinMap::new();
+    p_map.generate_seeded_vec_map(16, 16, 3);
+    p_map.set_seamless(true);
+    let xs = grid_coords(0.0, 0.37, 40);
+    let ys = grid_coords(0.0, 0.37, 40);
+    assert_eq!(batch(&p_map, &xs, &ys), scalar(&p_map, &xs, &ys));
+}
+
+#[test]
+fn handles_unsorted_coordinates() {
+    let p_map = hashed_map();
+    let xs = [5.5, -1.25, 5.75, 300.0, 0.0];
+    let ys = [2.0, -7.5, 2.1];
+    assert_eq!(batch(&p_map, &xs, &ys), scalar(&p_map, &xs, &ys));
+}
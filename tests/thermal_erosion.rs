@@ -0,0 +1,62 @@
+use terrain_generator::erosion::ThermalErosion;
+use terrain_generator::height_grid::HeightGrid;
+use terrain_generator::perlin_noise::PerlinMap;
+
+fn total_height(grid: &HeightGrid) -> f64 {
+    grid.data().iter().map(|h| *h as f64).sum()
+}
+
+fn steepest_side_slope(grid: &HeightGrid) -> f32 {
+    let mut steepest: f32 = 0.0;
+    for y in 0 .. grid.height() {
+        for x in 0 .. grid.width() {
+            if x + 1 < grid.width() {
+                steepest = steepest.max((grid.get(x, y) - grid.get(x + 1, y)).abs());
+            }
+            if y + 1 < grid.height() {
+                steepest = steepest.max((grid.get(x, y) - grid.get(x, y + 1)).abs());
+            }
+        }
+    }
+    steepest / grid.step()
+}
+
+#[test]
+fn conserves_mass_on_noise() {
+    let mut perlin_map = PerlinMap::new();
+    perlin_map.set_hashed(42);
+    let mut grid = HeightGrid::sample(&perlin_map, 0.0, 0.0, 0.05, 64, 64);
+    let before = total_height(&grid);
+
+    grid.apply(&ThermalErosion { iterations: 200, talus_angle: 10.0, ..Default::default() });
+
+    assert!((total_height(&grid) - before).abs() < 1e-3, "total height changed from {} to {}", before, total_height(&grid));
+}
+
+#[test]
+fn conserves_mass_at_the_edges() {
+    // A spike in the corner can only spread into the grid, nothing may slide off the edge
+    let mut grid = HeightGrid::new(16, 16);
+    grid.set(0, 0, 50.0);
+    grid.set(15, 8, 20.0);
+
+    grid.apply(&ThermalErosion { iterations: 500, talus_angle: 30.0, ..Default::default() });
+
+    assert!((total_height(&grid) - 70.0).abs() < 1e-3, "total height changed from 70 to {}", total_height(&grid));
+}
+
+#[test]
+fn relaxes_cliffs_towards_the_talus_angle() {
+    // A wall 10 units high between two flat halves
+    let mut grid = HeightGrid::new(32, 8);
+    for y in 0 .. 8 {
+        for x in 16 .. 32 {
+            grid.set(x, y, 10.0);
+        }
+    }
+
+    let thermal = ThermalErosion { iterations: 2000, talus_angle: 45.0, ..Default::default() };
+    grid.apply(&thermal);
+
+    assert!(steepest_side_slope(&grid) < 1.05, "slope {} is still steeper than the talus angle", steepest_side_slope(&grid));
+}